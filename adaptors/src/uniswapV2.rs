@@ -24,7 +24,8 @@ mod uniswapV2_mod {
     use eyre::Result;
     use arbitrage_engine::{
         utils::logger::{logText, logObject},
-        graph::cycle::get_negative_cycle_quick
+        graph::cycle::get_negative_cycle_quick,
+        decorated_edge::DecoratedEdge
     };
     use futures::future::join_all;
     use super::super::utils::{
@@ -218,18 +219,18 @@ mod uniswapV2_mod {
                     logText("---");
                 }
 
-                // Add rates as nodes to graph
+                // Add rates as edges to graph, as -ln(rate) so that a profitable cycle is a negative cycle
                 for pair in pairs_with_price.iter() {
                     graph.add_edge(
                         *nodes.get(&pair.token0).unwrap(), 
                         *nodes.get(&pair.token1).unwrap(),
-                        pair.token0_to_token1_rate
+                        DecoratedEdge::rate_to_weight(pair.token0_to_token1_rate)
                     );
 
                     graph.add_edge(
                         *nodes.get(&pair.token1).unwrap(), 
                         *nodes.get(&pair.token0).unwrap(),
-                        pair.token1_to_token0_rate
+                        DecoratedEdge::rate_to_weight(pair.token1_to_token0_rate)
                     );
                 }
 
@@ -19,10 +19,12 @@ mod utils_mod {
         U256::exp10(36).checked_div(x).unwrap()
     }
 
+    // x / 1e18 as f64, e.g. a rate scaled by 1e18
+    // Lossy beyond f64's 53 bits of precision, so check profits on raw reserves with exact integer maths
     pub fn u256_to_f64(x: U256) -> f64 {
         let (integer, mantissa) = x.div_mod(one_ether());
-        // So this will error if our integer larger than 1e18?
-        let integer_as_f64 = integer.as_u64() as f64;
+        // Integer can be up to ~1.2e59, past u64, so convert it 64 bit word by word rather than with as_u64()
+        let integer_as_f64 = integer.0.iter().rev().fold(0.0, |sum, word| sum * 2_f64.powi(64) + *word as f64);
         // Mantissa is % 1e18, so cannot be bigger than 1e18 by definition
         // u64 as safely handle numbers up to ~1e19
         let mantissa_as_f64 = mantissa.as_u64() as f64 / 10_u64.pow(18) as f64;
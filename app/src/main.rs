@@ -1,8 +1,12 @@
+use arbitrage_engine::arbitrage_service::ArbitrageService;
 use eyre::Result;
 use request_handler::core::RequestHandler;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    RequestHandler::listen_and_serve().await?;
+    // Graph lives for the whole process, every request is served against the same in-memory state
+    let service = ArbitrageService::new();
+    let mut handler = RequestHandler::new(service);
+    handler.listen_and_serve().await?;
     Ok(())
 }
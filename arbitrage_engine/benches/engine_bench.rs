@@ -0,0 +1,194 @@
+use arbitrage_engine::engine::{
+    find_cycles, get_all_negative_cycles_bounded, get_negative_cycle_incremental,
+    get_negative_cycle_quick, get_negative_cycles_from_super_source, get_top_k_negative_cycles,
+    search_by_component,
+};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use petgraph::{graph::Graph, prelude::NodeIndex};
+
+// Deterministic pseudo-random numbers, so every run benchmarks the same graph
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+}
+
+// Token graph with `edges_per_node` pools out of every token.
+// Rates follow a hidden price per token less a 0.3% fee, so there is no arbitrage unless we add one.
+fn token_graph(node_count: usize, edges_per_node: usize, with_arbitrage: bool) -> Graph<u32, f64> {
+    let mut rng = Lcg(42);
+    let mut graph: Graph<u32, f64> = Graph::new();
+    let nodes: Vec<NodeIndex> = (0..node_count as u32).map(|i| graph.add_node(i)).collect();
+    let log_prices: Vec<f64> = (0..node_count)
+        .map(|_| (rng.next() % 10_000) as f64 / 1_000.0)
+        .collect();
+    let fee_weight = -(0.997_f64.ln());
+
+    for source in 0..node_count {
+        for _ in 0..edges_per_node {
+            let target = (rng.next() as usize) % node_count;
+            if target == source {
+                continue;
+            }
+            let weight = log_prices[target] - log_prices[source] + fee_weight;
+            graph.add_edge(nodes[source], nodes[target], weight);
+        }
+    }
+
+    if with_arbitrage {
+        let (a, b, c) = (
+            nodes[node_count / 2],
+            nodes[node_count / 3],
+            nodes[node_count / 5],
+        );
+        graph.add_edge(a, b, -0.01);
+        graph.add_edge(b, c, -0.01);
+        graph.add_edge(c, a, -0.01);
+    }
+
+    graph
+}
+
+fn complete_graph(node_count: usize) -> Graph<u32, f64> {
+    let mut rng = Lcg(7);
+    let mut graph: Graph<u32, f64> = Graph::new();
+    let nodes: Vec<NodeIndex> = (0..node_count as u32).map(|i| graph.add_node(i)).collect();
+    for source in nodes.iter() {
+        for target in nodes.iter() {
+            if source != target {
+                let weight = (rng.next() % 1_000) as f64 / 1_000.0 - 0.45;
+                graph.add_edge(*source, *target, weight);
+            }
+        }
+    }
+    graph
+}
+
+fn bench_negative_cycle_quick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_negative_cycle_quick");
+    group.sample_size(10);
+    for node_count in [100, 1000] {
+        let graph = token_graph(node_count, 8, false);
+        group.bench_with_input(
+            BenchmarkId::new("no_arbitrage", node_count),
+            &graph,
+            |b, graph| b.iter(|| get_negative_cycle_quick(graph)),
+        );
+        let graph = token_graph(node_count, 8, true);
+        group.bench_with_input(
+            BenchmarkId::new("arbitrage", node_count),
+            &graph,
+            |b, graph| b.iter(|| get_negative_cycle_quick(graph)),
+        );
+    }
+    group.finish();
+}
+
+fn bench_negative_cycles_from_super_source(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_negative_cycles_from_super_source");
+    group.sample_size(10);
+    for node_count in [100, 1000] {
+        let graph = token_graph(node_count, 8, false);
+        group.bench_with_input(
+            BenchmarkId::new("no_arbitrage", node_count),
+            &graph,
+            |b, graph| b.iter(|| get_negative_cycles_from_super_source(graph)),
+        );
+        let graph = token_graph(node_count, 8, true);
+        group.bench_with_input(
+            BenchmarkId::new("arbitrage", node_count),
+            &graph,
+            |b, graph| b.iter(|| get_negative_cycles_from_super_source(graph)),
+        );
+    }
+    group.finish();
+}
+
+fn bench_negative_cycle_incremental(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_negative_cycle_incremental");
+    group.sample_size(10);
+    for node_count in [1000, 5000] {
+        let graph = token_graph(node_count, 8, false);
+        let all_edges: Vec<_> = graph.edge_indices().collect();
+        group.bench_with_input(
+            BenchmarkId::new("full_scan", node_count),
+            &graph,
+            |b, graph| {
+                b.iter(|| {
+                    let mut potentials: Vec<f64> = Vec::new();
+                    get_negative_cycle_incremental(graph, &mut potentials, &all_edges, 0.0)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_find_cycles(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_cycles");
+    group.sample_size(10);
+    for node_count in [6, 8] {
+        let graph = complete_graph(node_count);
+        group.bench_with_input(
+            BenchmarkId::new("complete", node_count),
+            &graph,
+            |b, graph| b.iter(|| find_cycles(graph)),
+        );
+    }
+    // Hop limit keeps the search polynomial, so much larger graphs are feasible
+    for node_count in [8, 32] {
+        let graph = complete_graph(node_count);
+        group.bench_with_input(
+            BenchmarkId::new("complete_max_hops_3", node_count),
+            &graph,
+            |b, graph| b.iter(|| get_all_negative_cycles_bounded(graph, Some(3))),
+        );
+    }
+    // complete/8 plus tokens with pools in one direction only, which can't be part of a cycle
+    let mut graph = complete_graph(8);
+    for i in 0..2000 {
+        let dead_end = graph.add_node(8 + i);
+        graph.add_edge(NodeIndex::new(i as usize % 8), dead_end, 0.0);
+    }
+    group.bench_with_input(
+        BenchmarkId::new("complete_with_dead_ends", 8),
+        &graph,
+        |b, graph| b.iter(|| get_all_negative_cycles_bounded(graph, None)),
+    );
+    group.bench_with_input(
+        BenchmarkId::new("complete_with_dead_ends_by_component", 8),
+        &graph,
+        |b, graph| {
+            b.iter(|| {
+                search_by_component(graph, |component, _| {
+                    get_all_negative_cycles_bounded(component, None)
+                })
+            })
+        },
+    );
+    // Same graphs as complete/n, but only the best 5
+    for node_count in [6, 8] {
+        let graph = complete_graph(node_count);
+        group.bench_with_input(
+            BenchmarkId::new("complete_top_5", node_count),
+            &graph,
+            |b, graph| b.iter(|| get_top_k_negative_cycles(graph, 5, None)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_negative_cycle_quick,
+    bench_negative_cycles_from_super_source,
+    bench_negative_cycle_incremental,
+    bench_find_cycles
+);
+criterion_main!(benches);
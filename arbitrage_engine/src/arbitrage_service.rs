@@ -1,26 +1,93 @@
 use crate::{
+    budget::Budget,
     decorated_edge::DecoratedEdge,
-    engine::{get_all_negative_cycles_0, get_negative_cycle_quick},
-    path::{DecoratedPath, Path}
+    engine::{
+        self, find_cycles_with_budget, get_all_negative_cycles_through_node_with_budget,
+        get_all_negative_cycles_with_budget, get_min_mean_cycle_with_budget,
+        get_negative_cycle_incremental_with_budget,
+        get_negative_cycle_through_node_quick_with_budget,
+        get_negative_cycles_from_super_source_with_budget, get_top_k_negative_cycles_with_budget,
+        MIN_MEAN_CYCLE_MAX_NODES,
+    },
+    gas::GasModel,
+    path::{DecoratedPath, Path},
+    scan_options::{ScanOptions, ScanResult},
 };
 use blake3::Hash;
 use petgraph::{
     graph::{EdgeReference, Graph},
     prelude::{EdgeIndex, NodeIndex},
     visit::EdgeRef,
+    Direction::{Incoming, Outgoing},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+    },
 };
-use std::collections::HashMap;
 
 pub trait IArbitrageService {
     fn upsert_path(&mut self, n0: &str, n1: &str, edge: DecoratedEdge) -> bool;
+    /// Remove edge n0 -> n1 for pool_address, e.g. pool drained or delisted
+    fn remove_edge(&mut self, n0: &str, n1: &str, pool_address: &str) -> bool;
+    /// Remove node and every edge into or out of it, e.g. token delisted
+    fn remove_node(&mut self, n: &str) -> bool;
     /// Returns all arbitrages found
     fn scan_arbitrages(&self) -> Vec<DecoratedPath>;
+    /// Returns all arbitrages found within the limits of `options`
+    /// If `options` has a timeout or relaxation budget, returns the arbitrages found before it ran out
+    fn scan_arbitrages_with_options(&self, options: &ScanOptions) -> ScanResult;
+    /// Returns the k most profitable arbitrages, most profitable first
+    fn scan_top_k(&self, k: usize) -> Vec<DecoratedPath>;
+    /// Returns the arbitrage with the best average return per trade, if any
+    /// Unlike the other scans this favours short arbitrages, e.g. when each trade costs the same gas
+    /// Cycles that fail DecoratedPath::is_verified() are passed over for the best of the rest
+    /// Memory grows with the square of the number of tokens that can trade round to each other, so a group of more than
+    /// MIN_MEAN_CYCLE_MAX_NODES such tokens is passed over. See engine::get_min_mean_cycle()
+    fn scan_best_mean_cycle(&self) -> Vec<DecoratedPath>;
+    /// Same as scan_best_mean_cycle, giving up without an arbitrage if `options` runs out of time or relaxations
+    /// Only the timeout, relaxation budget and how results are reported apply, not max_hops, anchors or top_k
+    /// Passing over a group of tokens for its size also counts as truncated
+    fn scan_best_mean_cycle_with_options(&self, options: &ScanOptions) -> ScanResult;
     /// Stops at first arbitrage found
+    /// Single Bellman-Ford pass over each component of the graph until one has an arbitrage, returning the most profitable it ran into
     fn scan_arbitrages_quick(&self) -> Vec<DecoratedPath>;
+    /// Stops at first arbitrage found within the limits of `options`
+    fn scan_arbitrages_quick_with_options(&self, options: &ScanOptions) -> ScanResult;
+    /// Same search as scan_arbitrages, but sends each arbitrage to `sender` as soon as it is found
+    /// Arbitrages arrive in discovery order, not sorted
+    /// Stops once `cancel` is set or the receiver is dropped
+    fn scan_arbitrages_stream(&self, sender: Sender<DecoratedPath>, cancel: &AtomicBool);
+    /// Same as scan_arbitrages_stream, also stopping once `options` runs out of time or relaxations
+    /// Only max_hops, the timeout and relaxation budget apply. Returns true if it ran out before finishing
+    fn scan_arbitrages_stream_with_options(
+        &self,
+        sender: Sender<DecoratedPath>,
+        cancel: &AtomicBool,
+        options: &ScanOptions,
+    ) -> bool;
+    /// Stops at first arbitrage found, only searching around edges added or updated since the previous call
+    /// Falls back to a full scan on the first call, and after any call that found an arbitrage
+    fn scan_arbitrages_incremental(&mut self) -> Vec<DecoratedPath>;
+    /// Same as scan_arbitrages_incremental, within the timeout and relaxation budget of `options`
+    /// Only the timeout, relaxation budget and how results are reported apply, not max_hops, anchors or top_k
+    /// A scan that runs out falls back to a full scan next time
+    fn scan_arbitrages_incremental_with_options(&mut self, options: &ScanOptions) -> ScanResult;
+    /// Resolve paths found by the engine for clients, dropping any that exact maths on pool reserves shows make no profit
+    /// See DecoratedPath::is_verified()
     fn _decorate_paths(&self, path: Vec<Path<String>>) -> Vec<DecoratedPath>;
-    // TODO - Provide streaming API for scan_arbitrages, so that we can use results as they become available without having to wait for entire algorithm to run
 }
 
+/// Rounding in sums of -ln(rate) leaves weights like -1e-15 on cycles that make no profit, so by default
+/// a cycle has to be lighter than this to count as an arbitrage. See ArbitrageService::set_min_profit_epsilon()
+pub const DEFAULT_MIN_PROFIT_EPSILON: f64 = 1e-9;
+
+/// Most combinations of pools to try for a cycle that fails verification, see ArbitrageService::_get_verified_path()
+/// upsert_path keeps two pools per pair, so this covers every combination for cycles of up to 6 hops
+const MAX_POOL_COMBINATIONS: usize = 64;
+
 /// Point of contact interacting with the arbitrage functionality
 pub struct ArbitrageService {
     /// Core directed graph data structure on which we perform the algorithm
@@ -31,10 +98,18 @@ pub struct ArbitrageService {
     node_indexes: HashMap<String, NodeIndex>,
     /// When we have found arbitrage path, resolve NodeIndex => nodeId
     nodes: HashMap<NodeIndex, String>,
-    /// DecoratedEdgeHash => EdgeIndex, 'Have we added this edge previously'?
+    /// DecoratedEdge unique id => EdgeIndex, 'Have we added an edge for this pool and direction previously'?
     edge_indexes: HashMap<Hash, EdgeIndex>,
     /// EdgeIndex => DecoratedEdge
     decorated_edges: HashMap<EdgeIndex, DecoratedEdge>,
+    /// NodeIndex => distance label left by the previous scan_arbitrages_incremental, empty if labels can't be reused
+    potentials: Vec<f64>,
+    /// Edges added or updated since the previous scan_arbitrages_incremental
+    touched_edges: HashSet<EdgeIndex>,
+    /// Gas cost estimates, for ranking arbitrages by net profit
+    gas_model: GasModel,
+    /// Minimum profit in log space, only cycles lighter than -min_profit_epsilon are arbitrages
+    min_profit_epsilon: f64,
 }
 
 impl ArbitrageService {
@@ -45,15 +120,304 @@ impl ArbitrageService {
             nodes: HashMap::new(),
             edge_indexes: HashMap::new(),
             decorated_edges: HashMap::new(),
+            potentials: Vec::new(),
+            touched_edges: HashSet::new(),
+            gas_model: GasModel::default(),
+            min_profit_epsilon: DEFAULT_MIN_PROFIT_EPSILON,
         }
     }
 
+    pub fn min_profit_epsilon(&self) -> f64 {
+        self.min_profit_epsilon
+    }
+
+    /// Minimum profit as a ratio of amount out to amount in, exp(min_profit_epsilon)
+    pub fn min_profit_ratio(&self) -> f64 {
+        DecoratedEdge::weight_to_rate(-self.min_profit_epsilon)
+    }
+
+    /// Only report cycles lighter than -epsilon, i.e. with profit_ratio above exp(epsilon). 0.0 for any negative cycle
+    /// Returns false, leaving the threshold unchanged, unless epsilon is finite and not negative
+    pub fn set_min_profit_epsilon(&mut self, epsilon: f64) -> bool {
+        if !(epsilon.is_finite() && epsilon >= 0.0) {
+            return false;
+        }
+        self.min_profit_epsilon = epsilon;
+        true
+    }
+
+    /// Same as set_min_profit_epsilon(), as a ratio of amount out to amount in, e.g. 1.001 for 0.1% profit
+    /// Returns false, leaving the threshold unchanged, unless ratio is finite and at least 1.0
+    pub fn set_min_profit_ratio(&mut self, ratio: f64) -> bool {
+        ratio >= 1.0 && self.set_min_profit_epsilon(-DecoratedEdge::rate_to_weight(ratio))
+    }
+
+    pub fn gas_model(&self) -> &GasModel {
+        &self.gas_model
+    }
+
+    pub fn set_gas_model(&mut self, gas_model: GasModel) {
+        self.gas_model = gas_model;
+    }
+
+    /// Update the gas price of one blockchain, returns false if the GasModel has no such node_type
+    pub fn set_gas_price(&mut self, node_type: usize, gas_price: f64) -> bool {
+        self.gas_model.set_gas_price(node_type, gas_price)
+    }
+
     pub fn node_count(&self) -> usize {
         self.graph.node_count()
     }
 
     pub fn edge_count(&self) -> usize {
-        self.graph.node_count()
+        self.graph.edge_count()
+    }
+
+    /// Resolve EdgeIndex => DecoratedEdge and NodeIndex => nodeId for a path found by the engine
+    fn _decorate_path(&self, path: &Path<String>) -> DecoratedPath {
+        let edge_index_to_decorated_edge =
+            |index: EdgeIndex| self.decorated_edges.get(&index).unwrap().clone();
+
+        let node_index_to_node = |index: NodeIndex| self.nodes.get(&index).unwrap().clone();
+
+        DecoratedPath::new(
+            path.nodes().into_iter().map(node_index_to_node).collect(),
+            path.edges()
+                .into_iter()
+                .map(edge_index_to_decorated_edge)
+                .collect(),
+        )
+    }
+
+    /// _decorate_paths(), plus alternative pools for each hop if requested
+    fn _decorate_paths_with_options(
+        &self,
+        path_collection: Vec<Path<String>>,
+        options: &ScanOptions,
+    ) -> Vec<DecoratedPath> {
+        path_collection
+            .iter()
+            .filter_map(|path| {
+                let mut decorated_path = self._decorate_path(path);
+                if !decorated_path.is_verified() {
+                    return None;
+                }
+                if options.include_alternatives {
+                    decorated_path.alternatives = self._get_alternative_edges(path);
+                }
+                Some(decorated_path)
+            })
+            .collect()
+    }
+
+    /// Decorate paths, sorted by weight, then if options.net_profit_asset is set rank them by net profit instead
+    /// Truncated to options.top_k either way, after dropping paths that fail verification
+    fn _get_scan_result(
+        &self,
+        paths: Vec<Path<String>>,
+        options: &ScanOptions,
+        budget: &Budget,
+    ) -> ScanResult {
+        // Move any path that fails on its lightest pools onto pools it passes on, which may make it a copy of another
+        let mut paths: Vec<Path<String>> = paths
+            .into_iter()
+            .filter_map(|path| self._get_verified_path(path))
+            .collect();
+        paths.sort_unstable();
+        paths.dedup();
+        let mut paths = self._decorate_paths_with_options(paths, options);
+        if let Some(asset) = &options.net_profit_asset {
+            let min_net_profit = options.min_net_profit.unwrap_or(0.0);
+            paths = paths
+                .into_iter()
+                .filter_map(|mut path| {
+                    path.net_profit = self._get_net_profit(&path, asset);
+                    path.net_profit
+                        .is_some_and(|net_profit| net_profit > min_net_profit)
+                        .then_some(path)
+                })
+                .collect();
+            paths.sort_by(|a, b| b.net_profit.unwrap().total_cmp(&a.net_profit.unwrap()));
+        }
+        if let Some(k) = options.top_k {
+            paths.truncate(k);
+        }
+        ScanResult {
+            paths,
+            truncated: budget.has_run_out(),
+        }
+    }
+
+    /// Profit of path's optimal trade less gas, valued in asset, None if either can't be valued in asset
+    fn _get_net_profit(&self, path: &DecoratedPath, asset: &str) -> Option<f64> {
+        let trade = path.optimal_trade?;
+        let profit = self._get_value(&path.nodes[0], trade.profit as f64, asset)?;
+        let mut gas_cost = 0.0;
+        for (native_token, amount) in self.gas_model.get_gas_costs(path) {
+            gas_cost += self._get_value(&native_token, amount, asset)?;
+        }
+        Some(profit - gas_cost)
+    }
+
+    /// amount of node valued in asset, at the best marginal rate of a pool trading node for asset
+    /// None if there is no such pool
+    fn _get_value(&self, node: &str, amount: f64, asset: &str) -> Option<f64> {
+        if node == asset {
+            return Some(amount);
+        }
+        let node_index = *self.node_indexes.get(node)?;
+        let asset_index = *self.node_indexes.get(asset)?;
+        self.graph
+            .edges_connecting(node_index, asset_index)
+            .map(|edge| *edge.weight())
+            .min_by(f64::total_cmp)
+            .map(|weight| amount * DecoratedEdge::weight_to_rate(weight))
+    }
+
+    /// For each hop of path, the edges between the same two nodes other than the one path trades through
+    fn _get_alternative_edges(&self, path: &Path<String>) -> Vec<Vec<DecoratedEdge>> {
+        let nodes = path.nodes();
+        path.edges()
+            .into_iter()
+            .enumerate()
+            .map(|(i, edge_index)| {
+                let mut alternatives: Vec<DecoratedEdge> = self
+                    .graph
+                    .edges_connecting(nodes[i], nodes[i + 1])
+                    .filter(|edge| edge.id() != edge_index)
+                    .map(|edge| self.decorated_edges.get(&edge.id()).unwrap().clone())
+                    .collect();
+                alternatives.sort_by(|a, b| a.weight.total_cmp(&b.weight));
+                alternatives
+            })
+            .collect()
+    }
+
+    /// search_by_component(), across threads with the parallel feature
+    fn _search_by_component(
+        &self,
+        search: impl Fn(&Graph<NodeIndex, f64>, &[EdgeIndex]) -> Vec<Path<NodeIndex>> + Sync,
+    ) -> Vec<Path<String>> {
+        #[cfg(feature = "parallel")]
+        return engine::search_by_component_parallel(&self.graph, search);
+        #[cfg(not(feature = "parallel"))]
+        return engine::search_by_component(&self.graph, search);
+    }
+
+    /// DecoratedPath::is_verified() for a cycle the engine found, so a search can pass over it rather than settle on it
+    /// Also true if the cycle passes through other pools on some hops, see _get_verified_path()
+    fn _is_verified(&self, path: &Path<String>) -> bool {
+        self._decorate_path(path).is_verified()
+            || self
+                ._get_pool_combinations(path)
+                .iter()
+                .any(|combination| self._decorate_path(combination).is_verified())
+    }
+
+    /// Cycle through the same tokens as `path` that passes DecoratedPath::is_verified(), None if there is none
+    /// Searches only follow the lightest pool for each pair, which may be too shallow for any trade to profit, e.g. a dust pool.
+    /// So if `path` fails, try its other pools, lightest combinations first
+    fn _get_verified_path(&self, path: Path<String>) -> Option<Path<String>> {
+        if self._decorate_path(&path).is_verified() {
+            return Some(path);
+        }
+        self._get_pool_combinations(&path)
+            .into_iter()
+            .find(|combination| self._decorate_path(combination).is_verified())
+    }
+
+    /// Cycles through the same tokens as `path` but other pools on at least one hop, lightest first
+    /// Only those lighter than -min_profit_epsilon, out of the first MAX_POOL_COMBINATIONS combinations
+    fn _get_pool_combinations(&self, path: &Path<String>) -> Vec<Path<String>> {
+        let nodes = path.nodes();
+        let path_edges = path.edges();
+        // Pools for each hop, lightest first
+        let hops: Vec<Vec<EdgeIndex>> = (0..path_edges.len())
+            .map(|i| {
+                let mut pools: Vec<EdgeReference<f64>> = self
+                    .graph
+                    .edges_connecting(nodes[i], nodes[i + 1])
+                    .collect();
+                pools.sort_by(|a, b| a.weight().total_cmp(b.weight()));
+                pools.into_iter().map(|edge| edge.id()).collect()
+            })
+            .collect();
+
+        let mut combinations: Vec<Path<String>> = Vec::new();
+        // Pool of each hop in the current combination, counting up like an odometer
+        let mut choice: Vec<usize> = vec![0; hops.len()];
+        for _ in 0..MAX_POOL_COMBINATIONS {
+            let mut combination: Path<String> = Path::new(nodes[0]);
+            for (hop, pools) in hops.iter().enumerate() {
+                combination.add_to_path(&self.graph, pools[choice[hop]]);
+            }
+            if combination.edges() != path_edges && combination.weight() < -self.min_profit_epsilon
+            {
+                combinations.push(combination);
+            }
+
+            let Some(hop) = (0..hops.len()).find(|hop| choice[*hop] + 1 < hops[*hop].len()) else {
+                break;
+            };
+            choice[hop] += 1;
+            choice[..hop].fill(0);
+        }
+        combinations.sort_unstable();
+        combinations
+    }
+
+    /// _is_verified() for a cycle in a component subgraph, `edges` as given by search_by_component()
+    fn _is_verified_in_component(
+        &self,
+        component: &Graph<NodeIndex, f64>,
+        edges: &[EdgeIndex],
+        cycle: &Path<NodeIndex>,
+    ) -> bool {
+        let mut path: Path<String> = Path::new(component[cycle.nodes()[0]]);
+        for edge in cycle.edges() {
+            path.add_to_path(&self.graph, edges[edge.index()]);
+        }
+        self._is_verified(&path)
+    }
+
+    /// Resolve anchor nodeIds => NodeIndex, skipping nodeIds we have never seen
+    fn _get_anchor_indexes(&self, anchors: &[String]) -> Vec<NodeIndex> {
+        anchors
+            .iter()
+            .filter_map(|anchor| self.node_indexes.get(anchor).cloned())
+            .collect()
+    }
+
+    /// Unique id of an edge already in the graph
+    fn _get_edge_id(&self, edge_index: EdgeIndex) -> Hash {
+        let (source, target) = self.graph.edge_endpoints(edge_index).unwrap();
+        DecoratedEdge::get_unique_id(
+            self.decorated_edges.get(&edge_index).unwrap(),
+            self.nodes.get(&source).unwrap(),
+            self.nodes.get(&target).unwrap(),
+        )
+    }
+
+    /// `Graph::remove_edge()` moves the last edge into the removed slot, invalidating the last EdgeIndex
+    /// So re-key the moved edge in edge_indexes and decorated_edges
+    fn _remove_edge_index(&mut self, edge_index: EdgeIndex) {
+        let last_edge_index = EdgeIndex::new(self.graph.edge_count() - 1);
+        self.edge_indexes.remove(&self._get_edge_id(edge_index));
+        let moved_edge_id = self._get_edge_id(last_edge_index);
+
+        self.graph.remove_edge(edge_index);
+        self.decorated_edges.remove(&edge_index);
+        // Removing an edge can't break potentials, only re-key touched_edges
+        self.touched_edges.remove(&edge_index);
+
+        if last_edge_index != edge_index {
+            let moved_edge = self.decorated_edges.remove(&last_edge_index).unwrap();
+            self.edge_indexes.insert(moved_edge_id, edge_index);
+            self.decorated_edges.insert(edge_index, moved_edge);
+            if self.touched_edges.remove(&last_edge_index) {
+                self.touched_edges.insert(edge_index);
+            }
+        }
     }
 }
 
@@ -68,10 +432,23 @@ impl IArbitrageService for ArbitrageService {
     /// We will not add the reverse edge here - up to the client
     /// Return true if graph updated, false if not
     fn upsert_path(&mut self, n0: &str, n1: &str, edge: DecoratedEdge) -> bool {
-        // If current edge exists, nothing to do
-        let edge_hash = DecoratedEdge::get_unique_id(&edge);
-        if self.edge_indexes.contains_key(&edge_hash) {
-            return false;
+        let edge_hash = DecoratedEdge::get_unique_id(&edge, n0, n1);
+        if let Some(existing_edge_index) = self.edge_indexes.get(&edge_hash) {
+            let existing_edge_index = *existing_edge_index;
+            let existing_decorated_edge = self.decorated_edges.get(&existing_edge_index).unwrap();
+
+            // If current edge exists with the same contents, nothing to do
+            if DecoratedEdge::get_content_hash(existing_decorated_edge)
+                == DecoratedEdge::get_content_hash(&edge)
+            {
+                return false;
+            }
+
+            // Price update for a pool we already have, update in place
+            *self.graph.edge_weight_mut(existing_edge_index).unwrap() = edge.weight;
+            self.decorated_edges.insert(existing_edge_index, edge);
+            self.touched_edges.insert(existing_edge_index);
+            return true;
         };
 
         // Add n0 if not yet existing
@@ -109,6 +486,7 @@ impl IArbitrageService for ArbitrageService {
             let new_edge_index = self.graph.add_edge(n0_index, n1_index, edge.weight);
             self.edge_indexes.insert(edge_hash, new_edge_index);
             self.decorated_edges.insert(new_edge_index, edge);
+            self.touched_edges.insert(new_edge_index);
             return true;
         }
 
@@ -131,15 +509,15 @@ impl IArbitrageService for ArbitrageService {
             // Update graph edge weight
             let edge_weight_to_update = self.graph.edge_weight_mut(existing_edge_index).unwrap();
             *edge_weight_to_update = edge.weight;
-            let existing_decorated_edge = self.decorated_edges.get(&existing_edge_index).unwrap();
             // Update edge_indexes
-            let existing_edge_hash = DecoratedEdge::get_unique_id(existing_decorated_edge);
+            let existing_edge_hash = self._get_edge_id(existing_edge_index);
             self.edge_indexes.remove(&existing_edge_hash);
             self.edge_indexes.insert(edge_hash, existing_edge_index);
             // Update decorated_edges
             self.decorated_edges.insert(existing_edge_index, edge);
+            self.touched_edges.insert(existing_edge_index);
 
-            return true;
+            true
         // Replace existing_edges[1]
         } else if edge.weight > *existing_edges[1].weight() {
             let existing_edge_index = existing_edges[1].id();
@@ -147,50 +525,306 @@ impl IArbitrageService for ArbitrageService {
             // Update graph edge weight
             let edge_weight_to_update = self.graph.edge_weight_mut(existing_edge_index).unwrap();
             *edge_weight_to_update = edge.weight;
-            let existing_decorated_edge = self.decorated_edges.get(&existing_edge_index).unwrap();
             // Update edge_indexes
-            let existing_edge_hash = DecoratedEdge::get_unique_id(existing_decorated_edge);
+            let existing_edge_hash = self._get_edge_id(existing_edge_index);
             self.edge_indexes.remove(&existing_edge_hash);
             self.edge_indexes.insert(edge_hash, existing_edge_index);
             // Update decorated_edges
             self.decorated_edges.insert(existing_edge_index, edge);
+            self.touched_edges.insert(existing_edge_index);
 
-            return true;
+            true
         // Do nothing
         } else {
-            return false;
+            false
         }
     }
 
-    fn scan_arbitrages_quick(&self) -> Vec<DecoratedPath> {
-        let (_, path_option) = get_negative_cycle_quick(&self.graph);
-        match path_option {
-            None => Vec::new(),
-            Some(path) => Self::_decorate_paths(self, vec![path]),
+    /// Return true if graph updated, false if no such edge
+    fn remove_edge(&mut self, n0: &str, n1: &str, pool_address: &str) -> bool {
+        let (n0_index, n1_index) = match (self.node_indexes.get(n0), self.node_indexes.get(n1)) {
+            (Some(n0_index), Some(n1_index)) => (*n0_index, *n1_index),
+            _ => return false,
+        };
+
+        let edge_index = self
+            .graph
+            .edges_connecting(n0_index, n1_index)
+            .map(|edge| edge.id())
+            .find(|edge_index| {
+                self.decorated_edges.get(edge_index).unwrap().pool_address == pool_address
+            });
+
+        match edge_index {
+            None => false,
+            Some(edge_index) => {
+                self._remove_edge_index(edge_index);
+                true
+            }
         }
     }
 
+    /// Return true if graph updated, false if no such node
+    fn remove_node(&mut self, n: &str) -> bool {
+        let node_index = match self.node_indexes.get(n) {
+            None => return false,
+            Some(index) => *index,
+        };
+
+        // Remove edges one at a time, each removal can move another edge into a different EdgeIndex
+        while let Some(edge_index) = self
+            .graph
+            .first_edge(node_index, Outgoing)
+            .or_else(|| self.graph.first_edge(node_index, Incoming))
+        {
+            self._remove_edge_index(edge_index);
+        }
+
+        // Same story for nodes, `Graph::remove_node()` moves the last node into the removed slot
+        let last_node_index = NodeIndex::new(self.graph.node_count() - 1);
+        self.graph.remove_node(node_index);
+        self.nodes.remove(&node_index);
+        self.node_indexes.remove(n);
+
+        if last_node_index != node_index {
+            let moved_node = self.nodes.remove(&last_node_index).unwrap();
+            self.node_indexes.insert(moved_node.clone(), node_index);
+            self.nodes.insert(node_index, moved_node);
+        }
+        // Mirror the node move in potentials, nodes added since the previous scan start at 0.0 as in the engine
+        if !self.potentials.is_empty() {
+            self.potentials.resize(last_node_index.index() + 1, 0.0);
+            self.potentials.swap_remove(node_index.index());
+        }
+
+        true
+    }
+
+    fn scan_arbitrages_quick(&self) -> Vec<DecoratedPath> {
+        self.scan_arbitrages_quick_with_options(&ScanOptions::default())
+            .paths
+    }
+
+    fn scan_arbitrages_quick_with_options(&self, options: &ScanOptions) -> ScanResult {
+        let budget = options.budget();
+        let path_option = if options.anchors.is_empty() {
+            // Most profitable of the arbitrages found in one pass, within max_hops if we can
+            let max_hops = options.max_hops.unwrap_or(usize::MAX);
+            let mut paths = self._search_by_component(|component, edges| {
+                let paths = get_negative_cycles_from_super_source_with_budget(
+                    component,
+                    self.min_profit_epsilon,
+                    &|cycle| self._is_verified_in_component(component, edges, cycle),
+                    &budget,
+                );
+                // Stop searching the other components, which may be on other threads, once one has an arbitrage to return
+                if paths.iter().any(|path| path.edges().len() <= max_hops) {
+                    budget.cancel();
+                }
+                paths
+            });
+            paths.sort_unstable();
+            let path_option = match paths.iter().position(|path| path.edges().len() <= max_hops) {
+                Some(position) => Some(paths.swap_remove(position)),
+                None => paths.into_iter().next(),
+            };
+            match (path_option, options.max_hops) {
+                // Bellman-Ford found arbitrages, but they are too long, in which case look for a short one
+                (Some(path), Some(max_hops)) if path.edges().len() > max_hops => {
+                    let mut short_path: Option<Path<String>> = None;
+                    find_cycles_with_budget(&self.graph, Some(max_hops), &budget, &mut |path| {
+                        if path.weight() < -self.min_profit_epsilon && self._is_verified(&path) {
+                            short_path = Some(path);
+                            return false;
+                        }
+                        true
+                    });
+                    short_path
+                }
+                (path_option, _) => path_option,
+            }
+        } else {
+            self._get_anchor_indexes(&options.anchors)
+                .into_iter()
+                .find_map(|anchor| {
+                    get_negative_cycle_through_node_quick_with_budget(
+                        &self.graph,
+                        anchor,
+                        options.max_hops,
+                        self.min_profit_epsilon,
+                        &|cycle| self._is_verified(cycle),
+                        &budget,
+                    )
+                })
+        };
+
+        self._get_scan_result(path_option.into_iter().collect(), options, &budget)
+    }
+
     fn scan_arbitrages(&self) -> Vec<DecoratedPath> {
-        let path = get_all_negative_cycles_0(&self.graph);
-        return Self::_decorate_paths(self, path);
+        self.scan_arbitrages_with_options(&ScanOptions::default())
+            .paths
     }
 
-    fn _decorate_paths(&self, path_collection: Vec<Path<String>>) -> Vec<DecoratedPath> {
-        let edge_index_to_decorated_edge =
-            |index: EdgeIndex| self.decorated_edges.get(&index).unwrap();
+    fn scan_arbitrages_with_options(&self, options: &ScanOptions) -> ScanResult {
+        let budget = options.budget();
+        // Most profitable by weight need not be most profitable after gas, so only prune by weight without gas
+        let top_k = options.top_k.filter(|_| options.net_profit_asset.is_none());
+        if options.anchors.is_empty() {
+            // Search each strongly connected component on its own, skipping tokens that can't be part of a cycle
+            let paths = self._search_by_component(|component, edges| match top_k {
+                None => get_all_negative_cycles_with_budget(
+                    component,
+                    options.max_hops,
+                    self.min_profit_epsilon,
+                    &budget,
+                ),
+                Some(k) => get_top_k_negative_cycles_with_budget(
+                    component,
+                    k,
+                    options.max_hops,
+                    self.min_profit_epsilon,
+                    &|cycle| self._is_verified_in_component(component, edges, cycle),
+                    &budget,
+                ),
+            });
+            return self._get_scan_result(paths, options, &budget);
+        }
 
-        let node_index_to_node = |index: NodeIndex| self.nodes.get(&index).unwrap();
+        let anchors = self._get_anchor_indexes(&options.anchors);
+        let mut paths: Vec<Path<String>> = Vec::new();
+        for (i, anchor) in anchors.iter().enumerate() {
+            for path in get_all_negative_cycles_through_node_with_budget(
+                &self.graph,
+                *anchor,
+                options.max_hops,
+                self.min_profit_epsilon,
+                &budget,
+            ) {
+                // Arbitrage through several anchors is reported once, starting at the first of them
+                let path_nodes = path.nodes();
+                if !anchors[..i]
+                    .iter()
+                    .any(|earlier| path_nodes.contains(earlier))
+                {
+                    paths.push(path);
+                }
+            }
+        }
+        self._get_scan_result(paths, options, &budget)
+    }
 
-        path_collection
-            .into_iter()
-            .map(|path| DecoratedPath {
-                edges: path
-                    .edges()
-                    .into_iter()
-                    .map(edge_index_to_decorated_edge)
-                    .collect(),
-                nodes: path.nodes().into_iter().map(node_index_to_node).collect(),
+    fn scan_top_k(&self, k: usize) -> Vec<DecoratedPath> {
+        let options = ScanOptions {
+            top_k: Some(k),
+            ..Default::default()
+        };
+        self.scan_arbitrages_with_options(&options).paths
+    }
+
+    fn scan_best_mean_cycle(&self) -> Vec<DecoratedPath> {
+        self.scan_best_mean_cycle_with_options(&ScanOptions::default())
+            .paths
+    }
+
+    fn scan_best_mean_cycle_with_options(&self, options: &ScanOptions) -> ScanResult {
+        let budget = options.budget();
+        // Karp's tables grow with the square of the node count, so run it on each component and keep the best
+        let mean_weight = |path: &Path<String>| path.weight() / path.edges().len() as f64;
+        let too_large = AtomicBool::new(false);
+        let best_path = self
+            ._search_by_component(|component, edges| {
+                if component.node_count() > MIN_MEAN_CYCLE_MAX_NODES {
+                    too_large.store(true, Ordering::Relaxed);
+                    return vec![];
+                }
+                // Karp only gives the best cycle, so while it fails verification take its lightest edge out of the running and try again
+                let mut component = component.clone();
+                while let Some(cycle) = get_min_mean_cycle_with_budget(&component, &budget) {
+                    if cycle.weight() >= -self.min_profit_epsilon
+                        || self._is_verified_in_component(&component, edges, &cycle)
+                    {
+                        return vec![cycle];
+                    }
+                    let lightest_edge = cycle
+                        .edges()
+                        .into_iter()
+                        .min_by(|a, b| component[*a].total_cmp(&component[*b]))
+                        .unwrap();
+                    // Never on a walk Karp can pick, as no walk through it beats the infinite distance it starts from
+                    component[lightest_edge] = f64::INFINITY;
+                }
+                vec![]
             })
+            .into_iter()
+            .min_by(|a, b| mean_weight(a).total_cmp(&mean_weight(b)))
+            .filter(|path| path.weight() < -self.min_profit_epsilon);
+        let mut result = self._get_scan_result(best_path.into_iter().collect(), options, &budget);
+        result.truncated |= too_large.load(Ordering::Relaxed);
+        result
+    }
+
+    fn scan_arbitrages_stream(&self, sender: Sender<DecoratedPath>, cancel: &AtomicBool) {
+        self.scan_arbitrages_stream_with_options(sender, cancel, &ScanOptions::default());
+    }
+
+    fn scan_arbitrages_stream_with_options(
+        &self,
+        sender: Sender<DecoratedPath>,
+        cancel: &AtomicBool,
+        options: &ScanOptions,
+    ) -> bool {
+        // Cancelling stops the search wherever it is, not only once the next cycle closes
+        let budget = options.budget().with_cancel_flag(cancel);
+        find_cycles_with_budget(&self.graph, options.max_hops, &budget, &mut |path| {
+            if path.weight() >= -self.min_profit_epsilon {
+                return true;
+            }
+            let Some(path) = self._get_verified_path(path) else {
+                return true;
+            };
+            // Receiver dropped, nobody is listening anymore
+            sender.send(self._decorate_path(&path)).is_ok()
+        });
+        budget.has_run_out()
+    }
+
+    fn scan_arbitrages_incremental(&mut self) -> Vec<DecoratedPath> {
+        self.scan_arbitrages_incremental_with_options(&ScanOptions::default())
+            .paths
+    }
+
+    fn scan_arbitrages_incremental_with_options(&mut self, options: &ScanOptions) -> ScanResult {
+        let budget = options.budget();
+        let touched_edges: Vec<EdgeIndex> = if self.potentials.is_empty() {
+            self.graph.edge_indices().collect()
+        } else {
+            self.touched_edges.iter().cloned().collect()
+        };
+        self.touched_edges.clear();
+
+        // Taken out for the search, which also reads the rest of self to verify cycles
+        let mut potentials = std::mem::take(&mut self.potentials);
+        let path_option = get_negative_cycle_incremental_with_budget(
+            &self.graph,
+            &mut potentials,
+            &touched_edges,
+            self.min_profit_epsilon,
+            &|cycle| self._is_verified(cycle),
+            &budget,
+        );
+        // Keep the labels for next time, unless they are not feasible around a negative cycle
+        if path_option.is_none() {
+            self.potentials = potentials;
+        }
+        self._get_scan_result(path_option.into_iter().collect(), options, &budget)
+    }
+
+    fn _decorate_paths(&self, path_collection: Vec<Path<String>>) -> Vec<DecoratedPath> {
+        path_collection
+            .iter()
+            .map(|path| self._decorate_path(path))
+            .filter(DecoratedPath::is_verified)
             .collect::<Vec<DecoratedPath>>()
     }
 }
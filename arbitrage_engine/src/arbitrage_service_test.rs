@@ -1,8 +1,16 @@
 #[cfg(test)]
 mod tests {
     use crate::{
-        decorated_edge::DecoratedEdge,
-        arbitrage_service::{IArbitrageService, ArbitrageService}
+        decorated_edge::{DecoratedEdge, PoolReserves},
+        arbitrage_service::{IArbitrageService, ArbitrageService, DEFAULT_MIN_PROFIT_EPSILON},
+        engine::MIN_MEAN_CYCLE_MAX_NODES,
+        gas::{ChainGas, GasModel, SwapGas},
+        path::DecoratedPath,
+        scan_options::ScanOptions
+    };
+    use std::{
+        sync::{atomic::{AtomicBool, Ordering}, mpsc::channel},
+        thread,
     };
 
     #[test]
@@ -13,7 +21,8 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let is_updated = service.upsert_path("a", "b", edge);
         assert!(is_updated);
@@ -27,20 +36,91 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let duplicated_edge = DecoratedEdge {
             weight: 1.0,
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let _ = service.upsert_path("a", "b", edge);
         let is_updated = service.upsert_path("a", "b", duplicated_edge);
         assert!(!is_updated);
     }
 
+    #[test]
+    fn test_upsert_path_reserves_moved_into_data_is_an_update() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        // Reserve bytes that are also valid text, so the same bytes can be spelled out in data
+        let reserves = PoolReserves {
+            reserve_in: u128::from_ne_bytes(*b"0123456789abcdef"),
+            reserve_out: u128::from_ne_bytes(*b"ghijklmnopqrstuv"),
+            fee_bps: u32::from_ne_bytes(*b"wxyz")
+        };
+        let edge = DecoratedEdge {
+            weight: 1.0,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: "0x01".to_string(),
+            data: "data".to_string(),
+            reserves: Some(reserves)
+        };
+        let edge_with_reserves_in_data = DecoratedEdge {
+            data: "data0123456789abcdefghijklmnopqrstuvwxyz".to_string(),
+            reserves: None,
+            ..edge.clone()
+        };
+        assert!(service.upsert_path("a", "b", edge));
+        assert!(service.upsert_path("a", "b", edge_with_reserves_in_data));
+        assert_eq!(service.edge_count(), 1);
+    }
+
+    #[test]
+    fn test_upsert_path_price_update_for_same_pool_updates_in_place() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: "0x01".to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+        let _ = service.upsert_path("a", "b", new_decorated_edge(0.5));
+        let _ = service.upsert_path("b", "a", new_decorated_edge(0.5));
+        assert_eq!(service.edge_count(), 2);
+        assert!(service.scan_arbitrages().is_empty());
+
+        // New price for the same pool and direction replaces the old weight
+        assert!(service.upsert_path("b", "a", new_decorated_edge(-0.6)));
+        assert!(!service.upsert_path("b", "a", new_decorated_edge(-0.6)));
+        assert_eq!(service.edge_count(), 2);
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 1);
+        assert!((paths[0].weight - -0.1).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_upsert_path_same_pool_in_other_direction_is_new_edge() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let edge = DecoratedEdge {
+            weight: 1.0,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: "0x01".to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+        let reverse_edge = edge.clone();
+        assert!(service.upsert_path("a", "b", edge));
+        assert!(service.upsert_path("b", "a", reverse_edge));
+        assert_eq!(service.edge_count(), 2);
+    }
+
     #[test]
     fn test_upsert_path_two_paths_between_same_nodes_success() {
         let mut service: ArbitrageService = ArbitrageService::new();
@@ -49,14 +129,16 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let edge_1 = DecoratedEdge {
             weight: 1.0,
             protocol_type: 2,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let _ = service.upsert_path("a", "b", edge_0);
         let is_updated = service.upsert_path("a", "b", edge_1);
@@ -71,21 +153,24 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let edge_1 = DecoratedEdge {
             weight: 1.05,
             protocol_type: 2,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let new_edge = DecoratedEdge {
             weight: 1.0,
             protocol_type: 3,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let _ = service.upsert_path("a", "b", edge_0);
         let _ = service.upsert_path("a", "b", edge_1);
@@ -101,21 +186,24 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let edge_1 = DecoratedEdge {
             weight: 1.05,
             protocol_type: 2,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let new_edge = DecoratedEdge {
             weight: 1.10,
             protocol_type: 3,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let _ = service.upsert_path("a", "b", edge_0);
         let _ = service.upsert_path("a", "b", edge_1);
@@ -131,21 +219,24 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let edge_1 = DecoratedEdge {
             weight: 1.05,
             protocol_type: 2,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let new_edge = DecoratedEdge {
             weight: 0.90,
             protocol_type: 3,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
         let _ = service.upsert_path("a", "b", edge_0);
         let _ = service.upsert_path("a", "b", edge_1);
@@ -163,7 +254,8 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
 
         service.upsert_path("nodes[4]", "nodes[5]", new_decorated_edge(0.35));
@@ -198,7 +290,8 @@ mod tests {
             protocol_type: 1,
             node_type: 1,
             pool_address: "".to_string(),
-            data: "".to_string()
+            data: "".to_string(),
+            reserves: None
         };
 
         service.upsert_path("nodes[4]", "nodes[5]", new_decorated_edge(0.35));
@@ -219,8 +312,911 @@ mod tests {
 
         assert_eq!(service.node_count(), 8);
         let paths = service.scan_arbitrages();
-        assert_eq!(paths.len(), 1);
+        // Same as engine_test.get_all_negative_cycles_test_2, most negative cycle first
+        assert_eq!(paths.len(), 2);
         assert_eq!(paths[0].nodes.len(), 3);
         assert_eq!(paths[0].edges.len(), 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_remove_edge_success() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64, pool_address: &str| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: pool_address.to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+
+        service.upsert_path("a", "b", new_decorated_edge(0.5, "0x01"));
+        service.upsert_path("b", "a", new_decorated_edge(-0.6, "0x01"));
+        assert_eq!(service.scan_arbitrages().len(), 1);
+
+        assert!(service.remove_edge("b", "a", "0x01"));
+        assert_eq!(service.edge_count(), 1);
+        assert_eq!(service.node_count(), 2);
+        assert!(service.scan_arbitrages().is_empty());
+    }
+
+    #[test]
+    fn test_remove_edge_missing_edge_should_return_false() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let edge = DecoratedEdge {
+            weight: 1.0,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: "0x01".to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+        service.upsert_path("a", "b", edge);
+        assert!(!service.remove_edge("a", "b", "0x02"));
+        assert!(!service.remove_edge("b", "a", "0x01"));
+        assert!(!service.remove_edge("a", "c", "0x01"));
+        assert_eq!(service.edge_count(), 1);
+    }
+
+    // Removing the first edge moves the last edge into its EdgeIndex
+    #[test]
+    fn test_remove_edge_keeps_moved_edge_consistent() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64, pool_address: &str| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: pool_address.to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+
+        service.upsert_path("c", "d", new_decorated_edge(1.0, "0x00"));
+        service.upsert_path("a", "b", new_decorated_edge(0.5, "0x01"));
+        service.upsert_path("b", "a", new_decorated_edge(-0.6, "0x02"));
+        assert!(service.remove_edge("c", "d", "0x00"));
+
+        // Moved edge can still be found, deduplicated and removed
+        assert!(!service.upsert_path("b", "a", new_decorated_edge(-0.6, "0x02")));
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 1);
+        let mut pool_addresses: Vec<&str> = paths[0]
+            .edges
+            .iter()
+            .map(|edge| edge.pool_address.as_str())
+            .collect();
+        pool_addresses.sort();
+        assert_eq!(pool_addresses, vec!["0x01", "0x02"]);
+
+        assert!(service.remove_edge("b", "a", "0x02"));
+        assert_eq!(service.edge_count(), 1);
+        assert!(service.scan_arbitrages().is_empty());
+    }
+
+    #[test]
+    fn test_remove_node_success() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64, pool_address: &str| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: pool_address.to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+
+        service.upsert_path("a", "b", new_decorated_edge(0.5, "0x01"));
+        service.upsert_path("b", "a", new_decorated_edge(-0.6, "0x01"));
+        service.upsert_path("a", "c", new_decorated_edge(0.5, "0x02"));
+        service.upsert_path("c", "a", new_decorated_edge(0.5, "0x02"));
+        service.upsert_path("c", "d", new_decorated_edge(0.1, "0x03"));
+        service.upsert_path("d", "c", new_decorated_edge(-0.2, "0x03"));
+
+        // "a" is the first node, so "d" is moved into its NodeIndex
+        assert!(service.remove_node("a"));
+        assert!(!service.remove_node("a"));
+        assert_eq!(service.node_count(), 3);
+        assert_eq!(service.edge_count(), 2);
+
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 1);
+        let mut nodes: Vec<&str> = paths[0].nodes.iter().map(|node| node.as_str()).collect();
+        nodes.sort();
+        assert_eq!(nodes, vec!["c", "c", "d"]);
+
+        // Removed node can be added again
+        assert!(service.upsert_path("a", "d", new_decorated_edge(0.5, "0x04")));
+        assert_eq!(service.node_count(), 4);
+    }
+
+    #[test]
+    fn test_upsert_rate_scan_returns_profit_ratio() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_rate_edge = |rate: f64, pool_address: &str| {
+            DecoratedEdge::from_rate(rate, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // 1 a -> 2 b -> 1.2 a
+        service.upsert_path("a", "b", new_rate_edge(2.0, "0x01"));
+        service.upsert_path("b", "a", new_rate_edge(0.6, "0x02"));
+        service.upsert_path("a", "c", new_rate_edge(3.0, "0x03"));
+        service.upsert_path("c", "a", new_rate_edge(0.3, "0x04"));
+
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 1);
+        assert!((paths[0].profit_ratio - 1.2).abs() < 1e-9);
+        assert!((paths[0].edges[0].rate() * paths[0].edges[1].rate() - 1.2).abs() < 1e-9);
+
+        let paths = service.scan_arbitrages_quick();
+        assert_eq!(paths.len(), 1);
+        assert!((paths[0].profit_ratio - 1.2).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_from_rate_rejects_invalid_rates() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(DecoratedEdge::from_rate(rate, 1, 1, "".to_string(), "".to_string()).is_none());
+        }
+    }
+
+    #[test]
+    fn test_upsert_reserves_and_simulate() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_pool_edge = |reserve_in: u128, reserve_out: u128, pool_address: &str| {
+            let reserves = PoolReserves { reserve_in, reserve_out, fee_bps: 30 };
+            DecoratedEdge::from_reserves(reserves, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // 1 a -> 2 b -> 1.2 a before fees
+        assert!(service.upsert_path("a", "b", new_pool_edge(1_000_000, 2_000_000, "0x01")));
+        assert!(service.upsert_path("b", "a", new_pool_edge(5_000_000, 3_000_000, "0x02")));
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].simulate(1_000).unwrap().profit().unwrap() > 0);
+
+        // Trading the optimal amount beats trading a bit more or less
+        let trade = paths[0].optimal_trade.unwrap();
+        assert_eq!(paths[0].simulate(trade.amount_in).unwrap().profit(), Some(trade.profit));
+        assert!(paths[0].simulate(trade.amount_in * 9 / 10).unwrap().profit().unwrap() < trade.profit);
+        assert!(paths[0].simulate(trade.amount_in * 11 / 10).unwrap().profit().unwrap() < trade.profit);
+
+        // Reserves doubled at the same rate, so same weight, but still an update
+        assert!(service.upsert_path("a", "b", new_pool_edge(2_000_000, 4_000_000, "0x01")));
+        assert!(!service.upsert_path("a", "b", new_pool_edge(2_000_000, 4_000_000, "0x01")));
+        let paths = service.scan_arbitrages();
+        let edge = paths[0].edges.iter().find(|edge| edge.pool_address == "0x01").unwrap();
+        assert_eq!(edge.reserves.unwrap().reserve_in, 2_000_000);
+
+        // No reserves, no simulation
+        service.upsert_path("a", "b", DecoratedEdge::from_rate(2.0, 1, 1, "0x01".to_string(), "".to_string()).unwrap());
+        let paths = service.scan_arbitrages();
+        assert!(paths[0].simulate(1_000).is_err());
+        assert_eq!(paths[0].optimal_trade, None);
+    }
+
+    #[test]
+    fn test_scan_arbitrages_by_net_profit() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_pool_edge = |reserve_in: u128, reserve_out: u128, pool_address: &str| {
+            let reserves = PoolReserves { reserve_in, reserve_out, fee_bps: 30 };
+            DecoratedEdge::from_reserves(reserves, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // USDC -> x -> USDC is the better rate, but USDC -> y -> USDC has the deeper pools, so more profit
+        service.upsert_path("USDC", "x", new_pool_edge(1_000_000, 2_000_000, "0x01"));
+        service.upsert_path("x", "USDC", new_pool_edge(5_000_000, 3_000_000, "0x02"));
+        service.upsert_path("USDC", "y", new_pool_edge(100_000_000, 100_000_000, "0x03"));
+        service.upsert_path("y", "USDC", new_pool_edge(100_000_000, 105_000_000, "0x04"));
+        service.upsert_path("WETH", "USDC", DecoratedEdge::from_rate(2.0, 1, 1, "0x05".to_string(), "".to_string()).unwrap());
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].nodes.contains(&"x".to_string()));
+        assert!(paths[0].optimal_trade.unwrap().profit < paths[1].optimal_trade.unwrap().profit);
+
+        // No gas model, so ranked by profit alone. Anchored so profit is made in USDC
+        let mut options = ScanOptions {
+            anchors: vec!["USDC".to_string()],
+            net_profit_asset: Some("USDC".to_string()),
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths.len(), 2);
+        assert!(paths[0].nodes.contains(&"y".to_string()));
+        assert_eq!(paths[0].net_profit, Some(paths[0].optimal_trade.unwrap().profit as f64));
+
+        // 20000 + 2 * 90000 gas at 0.025 WETH is 5000 WETH, or 10000 USDC, more than USDC -> x -> USDC makes
+        service.set_gas_model(GasModel {
+            swaps: vec![SwapGas { protocol_type: 1, node_type: 1, gas: 90_000 }],
+            default_swap_gas: 150_000,
+            chains: vec![ChainGas { node_type: 1, native_token: "WETH".to_string(), gas_price: 0.025, base_gas: 20_000 }],
+        });
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].nodes.contains(&"y".to_string()));
+        let net_profit = paths[0].optimal_trade.unwrap().profit as f64 - 10_000.0;
+        assert!((paths[0].net_profit.unwrap() - net_profit).abs() < 1e-6);
+
+        options.min_net_profit = Some(net_profit);
+        assert!(service.scan_arbitrages_with_options(&options).paths.is_empty());
+
+        // Cheaper gas, so both are worth it again, and top_k picks by net profit rather than weight
+        assert!(service.set_gas_price(1, 0.0001));
+        assert!(!service.set_gas_price(2, 0.0001));
+        options.min_net_profit = None;
+        options.top_k = Some(1);
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].nodes.contains(&"y".to_string()));
+
+        // Can't value profit in a nodeId with no pools
+        options.net_profit_asset = Some("DAI".to_string());
+        assert!(service.scan_arbitrages_with_options(&options).paths.is_empty());
+    }
+
+    #[test]
+    fn test_min_profit_threshold() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_rate_edge = |rate: f64, pool_address: &str| {
+            DecoratedEdge::from_rate(rate, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // Round trip at 1.1 then 1 / 1.1 makes no profit, whatever rounding leaves in the weights
+        service.upsert_path("a", "b", new_rate_edge(1.1, "0x01"));
+        service.upsert_path("b", "c", new_rate_edge(1.3, "0x02"));
+        service.upsert_path("c", "a", new_rate_edge(1.0 / 1.1 / 1.3, "0x03"));
+        assert_eq!(service.min_profit_epsilon(), DEFAULT_MIN_PROFIT_EPSILON);
+        assert!(service.scan_arbitrages().is_empty());
+        assert!(service.scan_arbitrages_quick().is_empty());
+        assert!(service.scan_arbitrages_incremental().is_empty());
+        assert!(service.scan_best_mean_cycle().is_empty());
+
+        // 1% profit is an arbitrage, unless we ask for more
+        service.upsert_path("c", "d", new_rate_edge(2.0, "0x04"));
+        service.upsert_path("d", "c", new_rate_edge(0.505, "0x05"));
+        assert_eq!(service.scan_arbitrages().len(), 1);
+        assert_eq!(service.scan_arbitrages_incremental().len(), 1);
+        assert!(service.set_min_profit_ratio(1.02));
+        assert!((service.min_profit_epsilon() - 1.02_f64.ln()).abs() < 1e-15);
+        assert!((service.min_profit_ratio() - 1.02).abs() < 1e-15);
+        assert!(service.scan_arbitrages().is_empty());
+        assert!(service.scan_arbitrages_quick().is_empty());
+        assert!(service.scan_arbitrages_incremental().is_empty());
+
+        assert!(service.set_min_profit_epsilon(0.005));
+        assert_eq!(service.scan_arbitrages_quick().len(), 1);
+
+        // Invalid thresholds leave it unchanged
+        assert!(!service.set_min_profit_ratio(0.99));
+        assert!(!service.set_min_profit_epsilon(-0.1));
+        assert!(!service.set_min_profit_epsilon(f64::NAN));
+        assert_eq!(service.min_profit_epsilon(), 0.005);
+    }
+
+    #[test]
+    fn test_min_profit_threshold_over_several_hops() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_rate_edge = |rate: f64, pool_address: &str| {
+            DecoratedEdge::from_rate(rate, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // 0.07% per hop makes 0.21% in total, so passes 0.1% although no single hop does
+        service.upsert_path("a", "b", new_rate_edge(1.0007, "0x01"));
+        service.upsert_path("b", "c", new_rate_edge(1.0007, "0x02"));
+        service.upsert_path("c", "a", new_rate_edge(1.0007, "0x03"));
+        // 0.02% in total doesn't pass
+        service.upsert_path("c", "d", new_rate_edge(1.0001, "0x04"));
+        service.upsert_path("d", "c", new_rate_edge(1.0001, "0x05"));
+        assert!(service.set_min_profit_ratio(1.001));
+
+        assert_eq!(service.scan_arbitrages().len(), 1);
+        assert_eq!(service.scan_arbitrages_quick()[0].edges.len(), 3);
+        assert_eq!(service.scan_arbitrages_incremental()[0].edges.len(), 3);
+    }
+
+    #[test]
+    fn test_scan_arbitrages_verifies_reserves() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        // Weight from a lossy conversion of the rate, which the pool's reserves don't bear out
+        let new_decorated_edge = |weight: f64, reserves: Option<PoolReserves>, pool_address: &str| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: pool_address.to_string(),
+            data: "".to_string(),
+            reserves
+        };
+        let reserves = PoolReserves { reserve_in: 1_000_000, reserve_out: 1_000_000, fee_bps: 30 };
+
+        // a -> b -> a looks like an arbitrage by weight, but only pays fees
+        service.upsert_path("a", "b", new_decorated_edge(-0.2, Some(reserves), "0x01"));
+        service.upsert_path("b", "a", new_decorated_edge(-0.2, Some(reserves), "0x02"));
+        // c -> d -> c has no reserves, so is taken at its weight
+        service.upsert_path("c", "d", new_decorated_edge(-0.1, None, "0x03"));
+        service.upsert_path("d", "c", new_decorated_edge(-0.1, None, "0x04"));
+
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].nodes.contains(&"c".to_string()));
+        assert_eq!(service.scan_top_k(2), paths);
+        // a -> b -> a has the best average, so c -> d -> c is the best that passes
+        let best_mean_paths = service.scan_best_mean_cycle();
+        assert_eq!(best_mean_paths.len(), 1);
+        assert!(best_mean_paths[0].nodes.contains(&"c".to_string()));
+
+        let (sender, receiver) = channel();
+        service.scan_arbitrages_stream(sender, &AtomicBool::new(false));
+        let streamed: Vec<_> = receiver.iter().collect();
+        assert_eq!(streamed, paths);
+
+        // Once the reserves bear it out, it is an arbitrage after all
+        let reserves = PoolReserves { reserve_in: 1_000_000, reserve_out: 1_500_000, fee_bps: 30 };
+        service.upsert_path("a", "b", new_decorated_edge(-0.2, Some(reserves), "0x01"));
+        assert_eq!(service.scan_arbitrages().len(), 2);
+        assert_eq!(service.scan_best_mean_cycle().len(), 1);
+    }
+
+    #[test]
+    fn test_scan_arbitrages_passes_over_unverified() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_reserves_edge = |reserve_in: u128, reserve_out: u128, pool_address: &str| {
+            let reserves = PoolReserves { reserve_in, reserve_out, fee_bps: 30 };
+            DecoratedEdge::from_reserves(reserves, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // Dust pools look like 1.4% profit, but any whole amount in rounds down to nothing
+        service.upsert_path("a", "b", new_reserves_edge(100, 101, "0x01"));
+        service.upsert_path("b", "a", new_reserves_edge(100, 101, "0x02"));
+        // 0.5% profit on deep pools, less by weight, in a component of its own
+        service.upsert_path("c", "d", new_reserves_edge(1_000_000_000_000_000_000_000_000, 1_005_500_000_000_000_000_000_000, "0x03"));
+        service.upsert_path("d", "c", new_reserves_edge(1_000_000_000_000_000_000_000_000, 1_005_500_000_000_000_000_000_000, "0x04"));
+
+        let is_deep_cycle = |paths: Vec<DecoratedPath>| paths.len() == 1 && paths[0].nodes.contains(&"c".to_string());
+        assert!(is_deep_cycle(service.scan_arbitrages()));
+        assert!(is_deep_cycle(service.scan_arbitrages_quick()));
+        assert!(is_deep_cycle(service.scan_top_k(1)));
+        // Found again on every call
+        assert!(is_deep_cycle(service.scan_arbitrages_incremental()));
+        assert!(is_deep_cycle(service.scan_arbitrages_incremental()));
+        let options = ScanOptions {
+            anchors: vec!["a".to_string(), "c".to_string()],
+            ..Default::default()
+        };
+        assert!(is_deep_cycle(service.scan_arbitrages_quick_with_options(&options).paths));
+    }
+
+    #[test]
+    fn test_scan_arbitrages_through_deep_pool_next_to_dust_pool() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_reserves_edge = |reserve_in: u128, reserve_out: u128, pool_address: &str| {
+            let reserves = PoolReserves { reserve_in, reserve_out, fee_bps: 30 };
+            DecoratedEdge::from_reserves(reserves, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // Searches follow the dust pool for a -> b, lightest by far, but no whole amount makes a profit through it.
+        // The deep pool on the same hop makes 2.4%
+        service.upsert_path("a", "b", new_reserves_edge(10, 15, "0x01"));
+        service.upsert_path("a", "b", new_reserves_edge(1_000_000_000_000_000_000_000, 1_030_000_000_000_000_000_000, "0x02"));
+        service.upsert_path("b", "a", new_reserves_edge(1_000_000_000_000_000_000_000, 1_000_000_000_000_000_000_000, "0x03"));
+
+        let is_deep_cycle = |paths: Vec<DecoratedPath>| {
+            paths.len() == 1
+                && paths[0].edges.iter().any(|edge| edge.pool_address == "0x02")
+                && paths[0].optimal_trade.is_some_and(|trade| trade.profit > 0)
+        };
+        assert!(is_deep_cycle(service.scan_arbitrages()));
+        assert!(is_deep_cycle(service.scan_arbitrages_quick()));
+        assert!(is_deep_cycle(service.scan_top_k(1)));
+        assert!(is_deep_cycle(service.scan_arbitrages_incremental()));
+        let options = ScanOptions {
+            anchors: vec!["a".to_string()],
+            ..Default::default()
+        };
+        assert!(is_deep_cycle(service.scan_arbitrages_with_options(&options).paths));
+        assert!(is_deep_cycle(service.scan_arbitrages_quick_with_options(&options).paths));
+        let (sender, receiver) = channel();
+        service.scan_arbitrages_stream(sender, &AtomicBool::new(false));
+        assert!(is_deep_cycle(receiver.iter().collect()));
+
+        // Alternatives still list the dust pool
+        let options = ScanOptions {
+            include_alternatives: true,
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert!(paths[0].alternatives.iter().flatten().any(|edge| edge.pool_address == "0x01"));
+    }
+
+    // Same graph as test_scan_arbitrages_quick, which has 2 arbitrages
+    fn new_service_with_two_arbitrages() -> ArbitrageService {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: "".to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+
+        service.upsert_path("nodes[4]", "nodes[5]", new_decorated_edge(0.35));
+        service.upsert_path("nodes[5]", "nodes[4]", new_decorated_edge(-0.66));
+        service.upsert_path("nodes[4]", "nodes[7]", new_decorated_edge(0.37));
+        service.upsert_path("nodes[5]", "nodes[7]", new_decorated_edge(0.28));
+        service.upsert_path("nodes[7]", "nodes[5]", new_decorated_edge(0.28));
+        service.upsert_path("nodes[5]", "nodes[1]", new_decorated_edge(0.32));
+        service.upsert_path("nodes[0]", "nodes[4]", new_decorated_edge(0.38));
+        service.upsert_path("nodes[0]", "nodes[2]", new_decorated_edge(0.26));
+        service.upsert_path("nodes[7]", "nodes[3]", new_decorated_edge(0.39));
+        service.upsert_path("nodes[1]", "nodes[3]", new_decorated_edge(0.29));
+        service.upsert_path("nodes[2]", "nodes[7]", new_decorated_edge(0.34));
+        service.upsert_path("nodes[6]", "nodes[2]", new_decorated_edge(0.40));
+        service.upsert_path("nodes[3]", "nodes[6]", new_decorated_edge(0.52));
+        service.upsert_path("nodes[6]", "nodes[0]", new_decorated_edge(0.58));
+        service.upsert_path("nodes[6]", "nodes[4]", new_decorated_edge(0.93));
+        service
+    }
+
+    #[test]
+    fn test_scan_arbitrages_with_max_hops() {
+        let service = new_service_with_two_arbitrages();
+        assert_eq!(service.scan_arbitrages_with_options(&ScanOptions::default()).paths.len(), 2);
+
+        // 4 -> 7 -> 5 -> 4 is 3 hops, so only 4 -> 5 -> 4 is left
+        let options = ScanOptions {
+            max_hops: Some(2),
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].nodes.len(), 3);
+        assert!(paths[0].nodes.contains(&"nodes[4]".to_string()));
+        assert!(paths[0].nodes.contains(&"nodes[5]".to_string()));
+    }
+
+    #[test]
+    fn test_scan_arbitrages_with_anchors() {
+        let service = new_service_with_two_arbitrages();
+
+        // Both arbitrages pass through nodes[4] and nodes[5], only 4 -> 7 -> 5 -> 4 through nodes[7]
+        let options = ScanOptions {
+            anchors: vec!["nodes[7]".to_string()],
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].nodes, vec!["nodes[7]", "nodes[5]", "nodes[4]", "nodes[7]"]);
+
+        // Arbitrage through both anchors is reported once, starting at the first anchor
+        let options = ScanOptions {
+            anchors: vec!["nodes[5]".to_string(), "nodes[4]".to_string()],
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|path| path.nodes[0] == "nodes[5]"));
+        assert!(paths.iter().all(|path| path.nodes.last().unwrap() == "nodes[5]"));
+
+        // No arbitrage through nodes[0], unknown anchors are ignored
+        let options = ScanOptions {
+            anchors: vec!["nodes[0]".to_string(), "unknown".to_string()],
+            ..Default::default()
+        };
+        assert!(service.scan_arbitrages_with_options(&options).paths.is_empty());
+        assert!(service.scan_arbitrages_quick_with_options(&options).paths.is_empty());
+    }
+
+    #[test]
+    fn test_scan_arbitrages_quick_with_anchors() {
+        let service = new_service_with_two_arbitrages();
+        let options = ScanOptions {
+            anchors: vec!["nodes[7]".to_string()],
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_quick_with_options(&options).paths;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].nodes, vec!["nodes[7]", "nodes[5]", "nodes[4]", "nodes[7]"]);
+
+        // 4 -> 7 -> 5 -> 4 is too long
+        let options = ScanOptions {
+            anchors: vec!["nodes[7]".to_string()],
+            max_hops: Some(2),
+            ..Default::default()
+        };
+        assert!(service.scan_arbitrages_quick_with_options(&options).paths.is_empty());
+
+        let options = ScanOptions {
+            max_hops: Some(2),
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_quick_with_options(&options).paths;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].nodes.len(), 3);
+    }
+
+    #[test]
+    fn test_scan_arbitrages_with_budget() {
+        let service = new_service_with_two_arbitrages();
+        let result = service.scan_arbitrages_with_options(&ScanOptions::default());
+        assert_eq!(result.paths.len(), 2);
+        assert!(!result.truncated);
+
+        // Out of relaxations before finding anything
+        let options = ScanOptions {
+            max_relaxations: Some(0),
+            ..Default::default()
+        };
+        let result = service.scan_arbitrages_with_options(&options);
+        assert!(result.paths.is_empty());
+        assert!(result.truncated);
+        let result = service.scan_arbitrages_quick_with_options(&options);
+        assert!(result.paths.is_empty());
+        assert!(result.truncated);
+
+        // Enough to finish, so same as without a budget
+        let options = ScanOptions {
+            max_relaxations: Some(1_000),
+            timeout_ms: Some(60_000),
+            ..Default::default()
+        };
+        let result = service.scan_arbitrages_with_options(&options);
+        assert_eq!(result.paths, service.scan_arbitrages());
+        assert!(!result.truncated);
+        let result = service.scan_arbitrages_quick_with_options(&options);
+        assert_eq!(result.paths, service.scan_arbitrages_quick());
+        assert!(!result.truncated);
+
+        // Truncated scans still report what they found so far, whatever the budget
+        let mut truncated_count = 0;
+        for max_relaxations in 0..100 {
+            let options = ScanOptions {
+                max_relaxations: Some(max_relaxations),
+                anchors: vec!["nodes[4]".to_string()],
+                ..Default::default()
+            };
+            let result = service.scan_arbitrages_with_options(&options);
+            assert!(result.paths.len() <= 2);
+            if result.truncated {
+                truncated_count += 1;
+            } else {
+                assert_eq!(result.paths.len(), 2);
+            }
+        }
+        assert!(truncated_count > 0 && truncated_count < 100);
+    }
+
+    #[test]
+    fn test_scan_arbitrages_quick_stops_at_first_component() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: "".to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+        // Many separate arbitrages, each in a component of its own
+        for i in 0..1_000 {
+            service.upsert_path(&format!("a{}", i), &format!("b{}", i), new_decorated_edge(0.1));
+            service.upsert_path(&format!("b{}", i), &format!("a{}", i), new_decorated_edge(-0.2));
+        }
+
+        // Not enough to search every component, but plenty for the first to find its arbitrage
+        let options = ScanOptions {
+            max_relaxations: Some(500),
+            ..Default::default()
+        };
+        let result = service.scan_arbitrages_with_options(&options);
+        assert!(result.truncated);
+        let result = service.scan_arbitrages_quick_with_options(&options);
+        assert_eq!(result.paths.len(), 1);
+        assert!(!result.truncated);
+    }
+
+    #[test]
+    fn test_scan_with_budget_for_every_scan() {
+        let mut service = new_service_with_two_arbitrages();
+        let out_of_budget = ScanOptions {
+            max_relaxations: Some(0),
+            ..Default::default()
+        };
+        let enough_budget = ScanOptions {
+            max_relaxations: Some(1_000),
+            ..Default::default()
+        };
+
+        let result = service.scan_best_mean_cycle_with_options(&out_of_budget);
+        assert!(result.paths.is_empty());
+        assert!(result.truncated);
+        let result = service.scan_best_mean_cycle_with_options(&enough_budget);
+        assert_eq!(result.paths, service.scan_best_mean_cycle());
+        assert!(!result.truncated);
+
+        let (sender, receiver) = channel();
+        assert!(service.scan_arbitrages_stream_with_options(sender, &AtomicBool::new(false), &out_of_budget));
+        assert_eq!(receiver.iter().count(), 0);
+        let (sender, receiver) = channel();
+        assert!(!service.scan_arbitrages_stream_with_options(sender, &AtomicBool::new(false), &enough_budget));
+        assert_eq!(receiver.iter().count(), 2);
+
+        // Incremental scan that runs out starts from scratch next time, so still finds the arbitrages
+        let result = service.scan_arbitrages_incremental_with_options(&out_of_budget);
+        assert!(result.paths.is_empty());
+        assert!(result.truncated);
+        let result = service.scan_arbitrages_incremental_with_options(&enough_budget);
+        assert_eq!(result.paths.len(), 1);
+        assert!(!result.truncated);
+    }
+
+    #[test]
+    fn test_scan_top_k() {
+        let service = new_service_with_two_arbitrages();
+        assert!(service.scan_top_k(0).is_empty());
+
+        // 4 -> 5 -> 4 at -0.31 beats 4 -> 7 -> 5 -> 4 at -0.01
+        let paths = service.scan_top_k(1);
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].nodes.len(), 3);
+        assert_eq!(service.scan_top_k(2), service.scan_arbitrages());
+        assert_eq!(service.scan_top_k(10), service.scan_arbitrages());
+
+        // Top k within the other limits
+        let options = ScanOptions {
+            anchors: vec!["nodes[7]".to_string()],
+            top_k: Some(1),
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].nodes.len(), 4);
+    }
+
+    #[test]
+    fn test_scan_best_mean_cycle() {
+        let mut service = new_service_with_two_arbitrages();
+        // 4 -> 5 -> 4 averages -0.155 per trade, against -0.0033 for 4 -> 7 -> 5 -> 4
+        let paths = service.scan_best_mean_cycle();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].nodes.len(), 3);
+        assert_eq!(paths[0], service.scan_top_k(1)[0]);
+
+        // Cycles remain, but none of them are arbitrages
+        let new_decorated_edge = |weight: f64| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: "".to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+        service.upsert_path("nodes[5]", "nodes[4]", new_decorated_edge(0.66));
+        assert!(service.scan_best_mean_cycle().is_empty());
+
+        // Components are searched one at a time, and the best average among them wins
+        service.upsert_path("x", "y", new_decorated_edge(-0.1));
+        service.upsert_path("y", "x", new_decorated_edge(-0.3));
+        service.upsert_path("nodes[5]", "nodes[4]", new_decorated_edge(-0.66));
+        let paths = service.scan_best_mean_cycle();
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].nodes.contains(&"x".to_string()));
+        service.upsert_path("y", "x", new_decorated_edge(0.0));
+        assert!(service.scan_best_mean_cycle()[0].nodes.contains(&"nodes[4]".to_string()));
+        assert!(!service.scan_best_mean_cycle_with_options(&ScanOptions::default()).truncated);
+
+        // A ring too large for Karp's tables is passed over, even with a better average, and reported as truncated
+        let ring_size = MIN_MEAN_CYCLE_MAX_NODES + 1;
+        for i in 0..ring_size {
+            service.upsert_path(&format!("ring[{}]", i), &format!("ring[{}]", (i + 1) % ring_size), new_decorated_edge(-1.0));
+        }
+        let result = service.scan_best_mean_cycle_with_options(&ScanOptions::default());
+        assert!(result.truncated);
+        assert_eq!(result.paths.len(), 1);
+        assert!(result.paths[0].nodes.contains(&"nodes[4]".to_string()));
+    }
+
+    #[test]
+    fn test_scan_best_mean_cycle_passes_over_unverified() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_reserves_edge = |reserve_in: u128, reserve_out: u128, pool_address: &str| {
+            let reserves = PoolReserves { reserve_in, reserve_out, fee_bps: 30 };
+            DecoratedEdge::from_reserves(reserves, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+        };
+
+        // x -> y -> x has the best average by far, but no whole amount makes a profit through dust pools
+        service.upsert_path("x", "y", new_reserves_edge(10, 13, "0x01"));
+        service.upsert_path("y", "x", new_reserves_edge(10, 10, "0x02"));
+        // u -> v -> u makes 0.4% on deep pools
+        service.upsert_path("u", "v", new_reserves_edge(1_000_000_000_000_000_000_000, 1_010_000_000_000_000_000_000, "0x03"));
+        service.upsert_path("v", "u", new_reserves_edge(1_000_000_000_000_000_000_000, 1_000_000_000_000_000_000_000, "0x04"));
+        // x and u trade at a loss both ways, which puts both cycles in the same component
+        service.upsert_path("x", "u", new_reserves_edge(1_000_000_000_000_000_000_000, 900_000_000_000_000_000_000, "0x05"));
+        service.upsert_path("u", "x", new_reserves_edge(1_000_000_000_000_000_000_000, 900_000_000_000_000_000_000, "0x06"));
+
+        assert_eq!(service.scan_arbitrages().len(), 1);
+        let paths = service.scan_best_mean_cycle();
+        assert_eq!(paths.len(), 1);
+        assert!(paths[0].nodes.contains(&"u".to_string()));
+        assert!(paths[0].is_verified());
+
+        // Nothing left once the deep pools make no profit either
+        service.upsert_path("u", "v", new_reserves_edge(1_000_000_000_000_000_000_000, 1_000_000_000_000_000_000_000, "0x03"));
+        assert!(service.scan_best_mean_cycle().is_empty());
+    }
+
+    #[test]
+    fn test_scan_arbitrages_through_second_pool() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64, pool_address: &str| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: pool_address.to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+
+        // Arbitrage only exists through pool 0x02
+        service.upsert_path("a", "b", new_decorated_edge(0.1, "0x02"));
+        service.upsert_path("a", "b", new_decorated_edge(0.5, "0x01"));
+        service.upsert_path("b", "a", new_decorated_edge(-0.3, "0x03"));
+
+        let paths = service.scan_arbitrages();
+        assert_eq!(paths.len(), 1);
+        let pools: Vec<&str> = paths[0].edges.iter().map(|edge| edge.pool_address.as_str()).collect();
+        assert!(pools.contains(&"0x02"));
+        assert!(paths[0].alternatives.is_empty());
+        let quick_paths = service.scan_arbitrages_quick();
+        assert!(quick_paths[0].edges.iter().any(|edge| edge.pool_address == "0x02"));
+
+        // Other pool reported as the alternative for the a -> b hop
+        let options = ScanOptions {
+            include_alternatives: true,
+            ..Default::default()
+        };
+        let paths = service.scan_arbitrages_with_options(&options).paths;
+        assert_eq!(paths[0].alternatives.len(), 2);
+        for (i, edge) in paths[0].edges.iter().enumerate() {
+            if edge.pool_address == "0x02" {
+                assert_eq!(paths[0].alternatives[i].len(), 1);
+                assert_eq!(paths[0].alternatives[i][0].pool_address, "0x01");
+            } else {
+                assert!(paths[0].alternatives[i].is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_scan_arbitrages_stream_yields_all_arbitrages() {
+        let service = new_service_with_two_arbitrages();
+        let cancel = AtomicBool::new(false);
+        let (sender, receiver) = channel();
+
+        let paths: Vec<_> = thread::scope(|scope| {
+            scope.spawn(|| service.scan_arbitrages_stream(sender, &cancel));
+            receiver.iter().collect()
+        });
+        assert_eq!(paths.len(), 2);
+        assert!(paths.iter().all(|path| path.weight < 0.0));
+    }
+
+    #[test]
+    fn test_scan_arbitrages_stream_stops_when_cancelled() {
+        // Every pair of 8 tokens trades at a profit both ways, so every one of the 16064 cycles is an arbitrage
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |pool_address: String| DecoratedEdge {
+            weight: -0.01,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address,
+            data: "".to_string(),
+            reserves: None
+        };
+        for i in 0..8 {
+            for j in 0..8 {
+                if i != j {
+                    service.upsert_path(&format!("n{}", i), &format!("n{}", j), new_decorated_edge(format!("0x{}{}", i, j)));
+                }
+            }
+        }
+        let (sender, receiver) = channel();
+        service.scan_arbitrages_stream(sender, &AtomicBool::new(false));
+        let all_count = receiver.iter().count();
+        assert_eq!(all_count, 16064);
+
+        // Cancelled before it starts, nothing is sent
+        let (sender, receiver) = channel();
+        service.scan_arbitrages_stream(sender, &AtomicBool::new(true));
+        assert_eq!(receiver.iter().count(), 0);
+
+        // Act on the first arbitrage and cancel the rest of the search
+        let cancel = AtomicBool::new(false);
+        let (sender, receiver) = channel();
+        let first_path = thread::scope(|scope| {
+            scope.spawn(|| service.scan_arbitrages_stream(sender, &cancel));
+            let first_path = receiver.recv().unwrap();
+            cancel.store(true, Ordering::Relaxed);
+            first_path
+        });
+        assert!(first_path.weight < 0.0);
+        // Scan thread has finished, only what it sent before seeing cancel is left
+        assert!(receiver.iter().count() < all_count / 2);
+    }
+
+    #[test]
+    fn test_scan_arbitrages_stream_stops_when_cancelled_between_cycles() {
+        // 6 layers of 4 tokens, each trading with every token of the next layer, and the last layer back to the first.
+        // Every cycle takes 6 trades, so with max_hops of 5 the search walks thousands of paths without closing one
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |pool_address: String| DecoratedEdge {
+            weight: 0.0,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address,
+            data: "".to_string(),
+            reserves: None
+        };
+        for layer in 0..6 {
+            for i in 0..4 {
+                for j in 0..4 {
+                    let n0 = format!("l{}n{}", layer, i);
+                    let n1 = format!("l{}n{}", (layer + 1) % 6, j);
+                    service.upsert_path(&n0, &n1, new_decorated_edge(format!("{}{}", n0, n1)));
+                }
+            }
+        }
+        let options = ScanOptions {
+            max_hops: Some(5),
+            max_relaxations: Some(1000),
+            ..Default::default()
+        };
+
+        // Without cancelling, the search runs out of relaxations before it gets through
+        let (sender, receiver) = channel();
+        assert!(service.scan_arbitrages_stream_with_options(sender, &AtomicBool::new(false), &options));
+        assert_eq!(receiver.iter().count(), 0);
+
+        // Cancel is seen without a cycle to hand over, so the search stops long before running out
+        let (sender, receiver) = channel();
+        assert!(!service.scan_arbitrages_stream_with_options(sender, &AtomicBool::new(true), &options));
+        assert_eq!(receiver.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_scan_arbitrages_incremental() {
+        let mut service: ArbitrageService = ArbitrageService::new();
+        let new_decorated_edge = |weight: f64, pool_address: &str| DecoratedEdge {
+            weight,
+            protocol_type: 1,
+            node_type: 1,
+            pool_address: pool_address.to_string(),
+            data: "".to_string(),
+            reserves: None
+        };
+
+        service.upsert_path("a", "b", new_decorated_edge(0.5, "0x01"));
+        service.upsert_path("b", "a", new_decorated_edge(-0.4, "0x01"));
+        service.upsert_path("b", "c", new_decorated_edge(0.1, "0x02"));
+        service.upsert_path("c", "b", new_decorated_edge(0.1, "0x02"));
+        assert!(service.scan_arbitrages_incremental().is_empty());
+        assert!(service.scan_arbitrages_incremental().is_empty());
+
+        // New token and a price update, only these edges are rescanned
+        service.upsert_path("c", "d", new_decorated_edge(0.2, "0x03"));
+        service.upsert_path("d", "c", new_decorated_edge(-0.3, "0x03"));
+        let paths = service.scan_arbitrages_incremental();
+        assert_eq!(paths.len(), 1);
+        let mut nodes: Vec<&str> = paths[0].nodes.iter().map(|node| node.as_str()).collect();
+        nodes.sort();
+        assert_eq!(nodes, vec!["c", "c", "d"]);
+
+        // Arbitrage still present, found by the full rescan
+        assert_eq!(service.scan_arbitrages_incremental().len(), 1);
+
+        // Removing a node keeps the remaining labels usable
+        assert!(service.remove_node("d"));
+        assert!(service.scan_arbitrages_incremental().is_empty());
+        assert!(service.remove_node("a"));
+        service.upsert_path("c", "b", new_decorated_edge(-0.2, "0x02"));
+        assert_eq!(service.scan_arbitrages_incremental().len(), 1);
+    }
+}
@@ -0,0 +1,92 @@
+use std::{
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Reading the clock costs more than a relaxation, so only check the deadline this often
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+/// Limit on how much work a search may do, by wall-clock deadline and/or number of relaxations (edges examined).
+/// Searches given a Budget return what they found so far once it runs out, check is_exhausted() to tell whether they were cut short.
+/// Shared by reference between searches, including across threads.
+/// May also watch a flag of the caller's, e.g. a client cancelling a stream, so searches stop on it between cycles too.
+#[derive(Debug, Default)]
+pub struct Budget<'a> {
+    deadline: Option<Instant>,
+    max_relaxations: Option<u64>,
+    relaxations: AtomicU64,
+    exhausted: AtomicBool,
+    /// Set when the deadline or max_relaxations is reached, as opposed to cancel()
+    ran_out: AtomicBool,
+    /// Cancels the budget once set, see with_cancel_flag()
+    cancel_flag: Option<&'a AtomicBool>,
+}
+
+impl<'a> Budget<'a> {
+    /// Never runs out unless cancelled
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Budget running out `timeout` from now, and/or after `max_relaxations` relaxations
+    pub fn new(timeout: Option<Duration>, max_relaxations: Option<u64>) -> Self {
+        Budget {
+            deadline: timeout.map(|timeout| Instant::now() + timeout),
+            max_relaxations,
+            ..Default::default()
+        }
+    }
+
+    /// Same budget, also running out once `cancel_flag` is set, checked on every relaxation
+    pub fn with_cancel_flag(self, cancel_flag: &'a AtomicBool) -> Self {
+        Budget {
+            cancel_flag: Some(cancel_flag),
+            ..self
+        }
+    }
+
+    /// Spend one relaxation, false once the budget has run out
+    pub fn spend(&self) -> bool {
+        if self.exhausted.load(Ordering::Relaxed) {
+            return false;
+        }
+        if self
+            .cancel_flag
+            .is_some_and(|cancel_flag| cancel_flag.load(Ordering::Relaxed))
+        {
+            self.cancel();
+            return false;
+        }
+        if self.deadline.is_none() && self.max_relaxations.is_none() {
+            return true;
+        }
+
+        let relaxations = self.relaxations.fetch_add(1, Ordering::Relaxed) + 1;
+        let out_of_relaxations = self.max_relaxations.is_some_and(|max| relaxations > max);
+        let out_of_time = relaxations.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+            && self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline);
+        if out_of_relaxations || out_of_time {
+            self.ran_out.store(true, Ordering::Relaxed);
+            self.cancel();
+            return false;
+        }
+        true
+    }
+
+    /// Run out now, e.g. once another thread has found what we were looking for
+    pub fn cancel(&self) {
+        self.exhausted.store(true, Ordering::Relaxed);
+    }
+
+    /// Run out or cancelled, so searches should stop
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+
+    /// Exhausted by reaching the deadline or max_relaxations rather than by cancel(), i.e. the search may have missed something it was asked for
+    pub fn has_run_out(&self) -> bool {
+        self.ran_out.load(Ordering::Relaxed)
+    }
+}
@@ -1,11 +1,18 @@
 use blake3::{Hash, Hasher};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+/// Fees are in basis points, out of this
+pub const FEE_DENOMINATOR: u32 = 10_000;
 
 /// Edge with metadata
 /// petgraph::add_edge returns EdgeIndex<Ix> type
 /// We will maintain an in-memory hashmap of EdgeIndex<Ix> => DecoratedEdge
 /// Create class to store these data structures
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct DecoratedEdge {
+    /// -ln(exchange rate), so that a profitable cycle of rates (product > 1) is a negative cycle of weights
+    /// Prefer `DecoratedEdge::from_rate()` over setting this directly
     pub weight: f64,
     /// int enum for protocol
     pub protocol_type: usize,
@@ -14,17 +21,156 @@ pub struct DecoratedEdge {
     /// Hex string address for pool
     pub pool_address: String,
     /// Miscellaneous data
+    #[serde(default)]
     pub data: String,
+    /// Pool state behind the rate, if known, for simulating trades of a given size. See DecoratedPath::simulate()
+    #[serde(default)]
+    pub reserves: Option<PoolReserves>,
+}
+
+/// Reserves and fee of a constant-product (Uniswap V2 style) pool, as seen from one direction of trade
+/// Amounts are in the smallest unit of each token, e.g. wei, and are decimal strings in JSON since they don't fit a double
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolReserves {
+    /// Reserve of the token we pay in, n0 for edge n0 -> n1
+    #[serde(with = "u128_string")]
+    pub reserve_in: u128,
+    /// Reserve of the token we receive, n1 for edge n0 -> n1
+    #[serde(with = "u128_string")]
+    pub reserve_out: u128,
+    /// Swap fee in basis points, e.g. 30 for Uniswap V2's 0.3%
+    pub fee_bps: u32,
+}
+
+impl PoolReserves {
+    /// UniswapV2Library.getAmountOut(), rounding down exactly as the pool does
+    /// Returns None where the pool would revert: zero amount_in, an empty pool, or a fee of 100% or more
+    pub fn get_amount_out(&self, amount_in: u128) -> Option<u128> {
+        if amount_in == 0
+            || self.reserve_in == 0
+            || self.reserve_out == 0
+            || self.fee_bps >= FEE_DENOMINATOR
+        {
+            return None;
+        }
+
+        // Intermediate products overflow u128 for realistic reserves, so widen them
+        let amount_in_with_fee = BigUint::from(amount_in) * (FEE_DENOMINATOR - self.fee_bps);
+        let numerator = &amount_in_with_fee * self.reserve_out;
+        let denominator = BigUint::from(self.reserve_in) * FEE_DENOMINATOR + amount_in_with_fee;
+        // Always less than reserve_out, so it fits
+        u128::try_from(numerator / denominator).ok()
+    }
+
+    /// Marginal exchange rate after fees, i.e. for an infinitesimal amount in
+    pub fn rate(&self) -> f64 {
+        self.reserve_out as f64 / self.reserve_in as f64
+            * f64::from(FEE_DENOMINATOR - self.fee_bps.min(FEE_DENOMINATOR))
+            / f64::from(FEE_DENOMINATOR)
+    }
 }
 
 impl DecoratedEdge {
-    pub fn get_unique_id(edge: &DecoratedEdge) -> Hash {
+    /// Build an edge from a raw exchange rate: amount out per amount in, after fees
+    /// Returns None if rate is not a positive finite number, as it has no -ln(rate) weight
+    pub fn from_rate(
+        rate: f64,
+        protocol_type: usize,
+        node_type: usize,
+        pool_address: String,
+        data: String,
+    ) -> Option<Self> {
+        if !rate.is_finite() || rate <= 0.0 {
+            return None;
+        }
+
+        Some(DecoratedEdge {
+            weight: DecoratedEdge::rate_to_weight(rate),
+            protocol_type,
+            node_type,
+            pool_address,
+            data,
+            reserves: None,
+        })
+    }
+
+    /// Build an edge from pool reserves, weighted by the marginal rate
+    /// Returns None if the pool has no marginal rate, e.g. it is empty or takes the whole amount as fee
+    pub fn from_reserves(
+        reserves: PoolReserves,
+        protocol_type: usize,
+        node_type: usize,
+        pool_address: String,
+        data: String,
+    ) -> Option<Self> {
+        let mut edge = DecoratedEdge::from_rate(
+            reserves.rate(),
+            protocol_type,
+            node_type,
+            pool_address,
+            data,
+        )?;
+        edge.reserves = Some(reserves);
+        Some(edge)
+    }
+
+    pub fn rate_to_weight(rate: f64) -> f64 {
+        -rate.ln()
+    }
+
+    pub fn weight_to_rate(weight: f64) -> f64 {
+        (-weight).exp()
+    }
+
+    /// Exchange rate this edge was built from
+    pub fn rate(&self) -> f64 {
+        DecoratedEdge::weight_to_rate(self.weight)
+    }
+
+    /// Identity of the edge, stable across price updates for the same pool
+    /// Keyed by (node_type, protocol_type, pool_address, direction n0 -> n1), not by weight
+    pub fn get_unique_id(edge: &DecoratedEdge, n0: &str, n1: &str) -> Hash {
         let mut hasher = Hasher::new();
-        hasher.update(&edge.weight.to_ne_bytes());
-        hasher.update(&edge.protocol_type.to_ne_bytes());
         hasher.update(&edge.node_type.to_ne_bytes());
-        hasher.update(edge.pool_address.as_bytes());
+        hasher.update(&edge.protocol_type.to_ne_bytes());
+        // Length-prefix variable-length fields so ("ab", "c") and ("a", "bc") hash differently
+        for field in [edge.pool_address.as_str(), n0, n1] {
+            hasher.update(&field.len().to_ne_bytes());
+            hasher.update(field.as_bytes());
+        }
 
         hasher.finalize()
     }
+
+    /// Hash of the mutable contents of the edge, only used to detect that an upsert changes nothing
+    pub fn get_content_hash(edge: &DecoratedEdge) -> Hash {
+        let mut hasher = Hasher::new();
+        hasher.update(&edge.weight.to_ne_bytes());
+        // Length-prefix data and tag whether reserves follow, so data bytes can't pass for reserves
+        hasher.update(&edge.data.len().to_ne_bytes());
+        hasher.update(edge.data.as_bytes());
+        hasher.update(&[edge.reserves.is_some() as u8]);
+        if let Some(reserves) = edge.reserves {
+            hasher.update(&reserves.reserve_in.to_ne_bytes());
+            hasher.update(&reserves.reserve_out.to_ne_bytes());
+            hasher.update(&reserves.fee_bps.to_ne_bytes());
+        }
+
+        hasher.finalize()
+    }
+}
+
+/// (De)serialize u128 as a decimal string
+/// JSON numbers past 2^53 lose precision in most clients, and serde can't buffer u128 for internally tagged enums like Request
+pub(crate) mod u128_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(D::Error::custom)
+    }
 }
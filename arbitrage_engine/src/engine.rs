@@ -1,51 +1,210 @@
 #![allow(dead_code, unused, nonstandard_style)]
 
 use super::{
+    budget::Budget,
     path::Path,
+    scratch::{
+        with_scratch, CircuitScratch, DfsScratch, SpfaScratch, CIRCUIT_SCRATCH, DFS_SCRATCH,
+        SPFA_SCRATCH,
+    },
     utils::{logObject, logText},
 };
 use petgraph::{
+    algo::tarjan_scc,
     graph::{EdgeReference, Graph},
     prelude::{EdgeIndex, NodeIndex},
     visit::EdgeRef,
     Direction::Outgoing,
 };
-use std::collections::{HashMap, HashSet, VecDeque};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::{cell::Cell, collections::BinaryHeap};
 
-/// An arbitrage path is a negative cycle in a graph where nodes are assets, and edges are exchange prices
-/// TODO - Which function for getting negative cycles is the most performant and/or produces the most useful results?
+// An arbitrage path is a negative cycle in a graph where nodes are assets, and edges are exchange prices
+// TODO - Which function for getting negative cycles is the most performant and/or produces the most useful results?
 
 // Method 2 for obtaining all negative cycles, sorted from most negative to least.
-// Uses find_cycles within Bellman_Ford, cycles_found() occurs on graphs with less noise, however already O(V) factor for outer loop in function body and duplicate work from encountering the same path.
-pub fn get_all_negative_cycles_1<N: Clone>(graph: &Graph<N, f64>) -> Vec<Path<N>> {
-    let mut paths: Vec<Path<N>> = Vec::new();
+// Collects the parent pointer cycles from Bellman-Ford for every source, however already O(V) factor for outer loop in function body and duplicate work from encountering the same path.
+// Only cycles lighter than -epsilon, see get_all_negative_cycles_with_budget(). 0.0 for any negative cycle.
+pub fn get_all_negative_cycles_1<N>(graph: &Graph<N, f64>, epsilon: f64) -> Vec<Path<N>> {
+    let mut negative_paths: Vec<Path<N>> = Vec::new();
     for node in graph.node_indices() {
-        let mut cycles_found = get_all_negative_cycles_for_source(graph, node);
-        paths.append(&mut cycles_found);
+        let mut cycles_found = get_all_negative_cycles_for_source(graph, node, epsilon);
+        negative_paths.append(&mut cycles_found);
     }
-    let mut negative_paths: Vec<Path<N>> = paths
-        .into_iter()
-        .filter(|path| path.weight() < 0.0)
+    // Same cycle is found from every source that reaches it, sorting puts the copies next to each other
+    negative_paths.sort_unstable();
+    negative_paths.dedup();
+    negative_paths
+}
+
+// get_all_negative_cycles_1() with sources spread across threads.
+// Cycles are merged in source order before sorting, so the result is the same as get_all_negative_cycles_1().
+#[cfg(feature = "parallel")]
+pub fn get_all_negative_cycles_1_parallel<N: Sync + Send>(
+    graph: &Graph<N, f64>,
+    epsilon: f64,
+) -> Vec<Path<N>> {
+    let nodes: Vec<NodeIndex> = graph.node_indices().collect();
+    let mut negative_paths: Vec<Path<N>> = nodes
+        .par_iter()
+        .flat_map_iter(|node| get_all_negative_cycles_for_source(graph, *node, epsilon))
         .collect();
     negative_paths.sort_unstable();
-    negative_paths.dedup_by(|a, b| a.weight().to_bits() == b.weight().to_bits());
+    negative_paths.dedup();
     negative_paths
 }
 
 // Method 1 for obtaining all negative cycles, sorted from most negative to least.
 // Uses find_cycles() on unfiltered graph, may suffer noise in the graph.
-pub fn get_all_negative_cycles_0<N: Clone>(graph: &Graph<N, f64>) -> Vec<Path<N>> {
-    let paths = find_cycles(graph);
-    let mut negative_paths: Vec<Path<N>> = paths
-        .into_iter()
-        .filter(|path| path.weight() < 0.0)
-        .collect();
+pub fn get_all_negative_cycles_0<N>(graph: &Graph<N, f64>) -> Vec<Path<N>> {
+    get_all_negative_cycles_bounded(graph, None)
+}
+
+// Same as get_all_negative_cycles_0(), but only cycles of at most `max_hops` edges.
+// The bound prunes the enumeration itself, so long cycles through a dense graph are never explored.
+pub fn get_all_negative_cycles_bounded<N>(
+    graph: &Graph<N, f64>,
+    max_hops: Option<usize>,
+) -> Vec<Path<N>> {
+    get_all_negative_cycles_with_budget(graph, max_hops, 0.0, &Budget::unlimited())
+}
+
+// Same as get_all_negative_cycles_bounded(), but only cycles lighter than -epsilon, and gives up once `budget` runs out, returning the cycles found so far.
+// `epsilon` is the minimum profit in log space, so that rounding in -ln(rate) sums doesn't pass for an arbitrage. 0.0 for any negative cycle.
+pub fn get_all_negative_cycles_with_budget<N>(
+    graph: &Graph<N, f64>,
+    max_hops: Option<usize>,
+    epsilon: f64,
+    budget: &Budget,
+) -> Vec<Path<N>> {
+    let mut negative_paths: Vec<Path<N>> = Vec::new();
+    find_cycles_with_budget(graph, max_hops, budget, &mut |cycle| {
+        if cycle.weight() < -epsilon {
+            negative_paths.push(cycle);
+        }
+        true
+    });
     negative_paths.sort_unstable();
     negative_paths
 }
 
+// Strongly connected components with at least one cycle, i.e. more than one node or a self loop.
+// A cycle never leaves its component, so tokens outside these (e.g. behind one-directional pools) can't be part of an arbitrage.
+// Nodes within each component are in NodeIndex order, so searches over a component start from the same nodes as over the whole graph.
+pub fn strongly_connected_components<N>(graph: &Graph<N, f64>) -> Vec<Vec<NodeIndex>> {
+    let mut components: Vec<Vec<NodeIndex>> = tarjan_scc(graph)
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1 || graph.find_edge(component[0], component[0]).is_some()
+        })
+        .collect();
+    for component in components.iter_mut() {
+        component.sort_unstable();
+    }
+    components
+}
+
+// Runs `search` on each of strongly_connected_components() as a graph of its own, and maps the cycles found back onto `graph`.
+// Same cycles as running `search` on the whole graph, but each search only sees the edges within its component,
+// so dead ends are never explored. Components are independent, so their searches may run in any order.
+// Subgraph nodes are weighted by their NodeIndex in `graph`, and `search` is also given subgraph EdgeIndex => EdgeIndex in `graph`.
+// Cycles are returned in component order, unsorted.
+pub fn search_by_component<N>(
+    graph: &Graph<N, f64>,
+    search: impl Fn(&Graph<NodeIndex, f64>, &[EdgeIndex]) -> Vec<Path<NodeIndex>>,
+) -> Vec<Path<N>> {
+    let components = strongly_connected_components(graph);
+    let positions = _component_positions(graph, &components);
+
+    let mut paths: Vec<Path<N>> = Vec::new();
+    for (component_id, component) in components.iter().enumerate() {
+        let (subgraph, edges) = _component_subgraph(graph, component_id, component, &positions);
+        for cycle in search(&subgraph, &edges) {
+            paths.push(_component_cycle_to_path(graph, &subgraph, &edges, &cycle));
+        }
+    }
+    paths
+}
+
+// search_by_component() with components spread across threads.
+// Cycles are merged in component order, so the result is the same as search_by_component(),
+// unless searches depend on each other, e.g. through a shared Budget that one of them cancels or runs out.
+#[cfg(feature = "parallel")]
+pub fn search_by_component_parallel<N: Sync + Send>(
+    graph: &Graph<N, f64>,
+    search: impl Fn(&Graph<NodeIndex, f64>, &[EdgeIndex]) -> Vec<Path<NodeIndex>> + Sync,
+) -> Vec<Path<N>> {
+    let components = strongly_connected_components(graph);
+    let positions = _component_positions(graph, &components);
+    components
+        .par_iter()
+        .enumerate()
+        .flat_map_iter(|(component_id, component)| {
+            let (subgraph, edges) = _component_subgraph(graph, component_id, component, &positions);
+            search(&subgraph, &edges)
+                .into_iter()
+                .map(|cycle| _component_cycle_to_path(graph, &subgraph, &edges, &cycle))
+                .collect::<Vec<Path<N>>>()
+        })
+        .collect()
+}
+
+// Node => (component, NodeIndex in component subgraph), None outside every component
+fn _component_positions<N>(
+    graph: &Graph<N, f64>,
+    components: &[Vec<NodeIndex>],
+) -> Vec<Option<(usize, NodeIndex)>> {
+    let mut positions: Vec<Option<(usize, NodeIndex)>> = vec![None; graph.node_count()];
+    for (component_id, component) in components.iter().enumerate() {
+        for (position, node) in component.iter().enumerate() {
+            positions[node.index()] = Some((component_id, NodeIndex::new(position)));
+        }
+    }
+    positions
+}
+
+// Same cycle as `cycle` in a component subgraph, in terms of `graph`
+fn _component_cycle_to_path<N>(
+    graph: &Graph<N, f64>,
+    subgraph: &Graph<NodeIndex, f64>,
+    edges: &[EdgeIndex],
+    cycle: &Path<NodeIndex>,
+) -> Path<N> {
+    let mut path: Path<N> = Path::new(subgraph[cycle.nodes()[0]]);
+    for edge in cycle.edges() {
+        path.add_to_path(graph, edges[edge.index()]);
+    }
+    path
+}
+
+// Subgraph of the edges within one component, and subgraph EdgeIndex => EdgeIndex in `graph`
+fn _component_subgraph<N>(
+    graph: &Graph<N, f64>,
+    component_id: usize,
+    component: &[NodeIndex],
+    positions: &[Option<(usize, NodeIndex)>],
+) -> (Graph<NodeIndex, f64>, Vec<EdgeIndex>) {
+    let mut subgraph: Graph<NodeIndex, f64> = Graph::with_capacity(component.len(), 0);
+    let mut edges: Vec<EdgeIndex> = Vec::new();
+    for node in component {
+        subgraph.add_node(*node);
+    }
+    for (position, node) in component.iter().enumerate() {
+        for edge in graph.edges(*node) {
+            if let Some((target_component, target_position)) = positions[edge.target().index()] {
+                if target_component == component_id {
+                    subgraph.add_edge(NodeIndex::new(position), target_position, *edge.weight());
+                    edges.push(edge.id());
+                }
+            }
+        }
+    }
+    (subgraph, edges)
+}
+
 // Attempts get_negative_cycle_for_source_quick for all nodes, stops if it finds a negative cycle
-pub fn get_negative_cycle_quick<N: Clone>(graph: &Graph<N, f64>) -> (bool, Option<Path<N>>) {
+pub fn get_negative_cycle_quick<N>(graph: &Graph<N, f64>) -> (bool, Option<Path<N>>) {
     for node in graph.node_indices() {
         let (negative_cycle_found, cycle) = get_negative_cycle_for_source_quick(graph, node);
         if negative_cycle_found {
@@ -55,205 +214,713 @@ pub fn get_negative_cycle_quick<N: Clone>(graph: &Graph<N, f64>) -> (bool, Optio
     (false, None)
 }
 
-// Modified queue-based Bellman-Ford algorithm. Only difference with get_negative_cycle_for_source_quick is that we call find_cycles() after a successful has_cycle() call.
-pub fn get_all_negative_cycles_for_source<N: Clone>(
+// get_negative_cycle_quick() with sources spread across threads.
+// Once any thread finds a negative cycle the others give up, so which cycle is returned may differ between runs.
+#[cfg(feature = "parallel")]
+pub fn get_negative_cycle_quick_parallel<N: Sync + Send>(
     graph: &Graph<N, f64>,
-    source: NodeIndex,
+) -> (bool, Option<Path<N>>) {
+    // Cancelled by the first thread to find a cycle
+    let found = Budget::unlimited();
+    let nodes: Vec<NodeIndex> = graph.node_indices().collect();
+    let cycle = nodes.par_iter().find_map_any(|node| {
+        let (negative_cycle_found, cycle) =
+            _get_negative_cycle_for_source_quick(graph, *node, 0.0, &|_| true, &found);
+        if negative_cycle_found {
+            found.cancel();
+        }
+        cycle
+    });
+    (cycle.is_some(), cycle)
+}
+
+// Queue-based Bellman-Ford from a virtual super-source with a 0.0 edge to every node, so a single pass covers the whole graph.
+// O(V * E) worst case, compared to O(V^2 * E) for get_negative_cycle_quick() running a full pass from each node in turn.
+// Returns every cycle among the parent pointers once the first is found, sorted from most negative to least. Empty if there is no negative cycle.
+pub fn get_negative_cycles_from_super_source<N>(graph: &Graph<N, f64>) -> Vec<Path<N>> {
+    get_negative_cycles_from_super_source_with_budget(graph, 0.0, &|_| true, &Budget::unlimited())
+}
+
+// Same as get_negative_cycles_from_super_source(), but only cycles lighter than -epsilon that `accept` agrees to, and gives up once `budget` runs out,
+// returning any such cycles among the parent pointers at that point. See _take_parent_cycles() for cycles that aren't.
+// `accept` is for what weights alone can't tell, e.g. exact maths on pool reserves. |_| true for any cycle.
+pub fn get_negative_cycles_from_super_source_with_budget<N>(
+    graph: &Graph<N, f64>,
+    epsilon: f64,
+    accept: &dyn Fn(&Path<N>) -> bool,
+    budget: &Budget,
 ) -> Vec<Path<N>> {
-    // Node => Weight of current shortest path from source.
-    let mut dist: HashMap<NodeIndex, f64> = HashMap::new();
-    // Node => Edge in current shortest path with node as target_node.
-    let mut edgeTo: HashMap<NodeIndex, Option<EdgeReference<f64>>> = HashMap::new();
-    let mut queue: VecDeque<NodeIndex> = VecDeque::new();
-    let mut on_queue: HashMap<NodeIndex, bool> = HashMap::new();
-    // Counter of relax operations
-    let mut counter = 0;
+    with_scratch(&SPFA_SCRATCH, |scratch| {
+        scratch.reset(graph.node_count());
+        // Counter of relax operations
+        let mut counter = 0;
 
-    for node in graph.node_indices() {
-        edgeTo.insert(node, None);
-        dist.insert(node, f64::MAX);
-        on_queue.insert(node, false);
-    }
+        // Every node is one 0.0 edge away from the super-source
+        for node in graph.node_indices() {
+            scratch.dist[node.index()] = 0.0;
+            scratch.queue.push_back(node);
+            scratch.on_queue[node.index()] = true;
+        }
 
-    queue.push_back(source);
-    on_queue.insert(source, true);
-    dist.insert(source, 0.0);
+        'search: while let Some(current_node) = scratch.queue.pop_front() {
+            scratch.on_queue[current_node.index()] = false;
 
-    while !queue.is_empty() {
-        let current_node = queue.pop_front().unwrap();
-        on_queue.insert(source, false);
+            // Iterate through all neighbours
+            for edge in graph.edges_directed(current_node, Outgoing) {
+                if !budget.spend() {
+                    break 'search;
+                }
+                let weight = edge.weight();
+                let target_node = edge.target();
 
-        // Iterate through all neighbours
-        for edge in graph.edges_directed(current_node, Outgoing) {
-            let weight = edge.weight();
-            let target_node = edge.target();
+                // Relax operation
+                if scratch.dist[target_node.index()]
+                    > scratch.dist[current_node.index()] + weight + RELAX_TOLERANCE
+                    && !scratch.banned.contains(&edge.id())
+                {
+                    scratch.dist[target_node.index()] = scratch.dist[current_node.index()] + weight;
+                    scratch.edge_to[target_node.index()] = Some(edge.id());
+                    counter += 1;
 
-            // Relax operation
-            if *dist.get(&target_node).unwrap() > dist.get(&current_node).unwrap() + weight {
-                *dist.get_mut(&target_node).unwrap() = dist.get(&current_node).unwrap() + weight;
-                *edgeTo.get_mut(&target_node).unwrap() = Some(edge);
-                counter += 1;
+                    if !scratch.on_queue[target_node.index()] {
+                        scratch.queue.push_back(target_node);
+                        scratch.on_queue[target_node.index()] = true;
+                    }
 
-                if !on_queue.get(&target_node).unwrap() {
-                    queue.push_back(target_node);
-                    on_queue.insert(target_node, true);
+                    // Check for cycle every V times we call relax.
+                    if counter % graph.node_count() == 0 {
+                        let mut cycles = _take_parent_cycles(graph, scratch, epsilon, accept, true);
+                        if !cycles.is_empty() {
+                            cycles.sort_unstable();
+                            return cycles;
+                        }
+                    }
                 }
+            }
+        }
+
+        // Emptied queue without finding cycle, or ran out of budget in which case settle for the cycles found so far
+        let mut cycles: Vec<Path<N>> = Vec::new();
+        if budget.is_exhausted() {
+            cycles = _take_parent_cycles(graph, scratch, epsilon, accept, true);
+            cycles.sort_unstable();
+        }
+        cycles
+    })
+}
+
+// Cycle with the lowest mean weight per edge, i.e. the best average return per trade, using Karp's algorithm.
+// Sum of weights favours long cycles, whereas with a fixed gas cost per hop we care about return per hop.
+// Returns the cycle whatever its weight, so check weight() < 0.0 for an arbitrage. None if the graph has no cycle.
+// O(V * E) time and O(V^2) memory for the walk tables, 16 bytes per entry, so only runs on graphs of up to MIN_MEAN_CYCLE_MAX_NODES nodes.
+pub fn get_min_mean_cycle<N>(graph: &Graph<N, f64>) -> Option<Path<N>> {
+    get_min_mean_cycle_with_budget(graph, &Budget::unlimited())
+}
+
+// Same as get_min_mean_cycle(), but gives up once `budget` runs out.
+// Karp needs walks of every length up to V before it can pick a cycle, so returns None rather than a partial result.
+// Also None, without allocating the walk tables, for a graph of more than MIN_MEAN_CYCLE_MAX_NODES nodes.
+pub fn get_min_mean_cycle_with_budget<N>(
+    graph: &Graph<N, f64>,
+    budget: &Budget,
+) -> Option<Path<N>> {
+    let n = graph.node_count();
+    if n == 0 || n > MIN_MEAN_CYCLE_MAX_NODES {
+        return None;
+    }
+
+    // dist[k * n + v] => weight of the lightest walk of exactly k edges ending at v, starting anywhere
+    // edge_to[k * n + v] => last edge of that walk
+    let mut dist: Vec<f64> = vec![f64::INFINITY; (n + 1) * n];
+    let mut edge_to: Vec<Option<EdgeIndex>> = vec![None; (n + 1) * n];
+    dist[..n].fill(0.0);
+    for k in 1..=n {
+        for edge in graph.edge_references() {
+            if !budget.spend() {
+                return None;
+            }
+            let previous = dist[(k - 1) * n + edge.source().index()];
+            if previous == f64::INFINITY {
+                continue;
+            }
+            let candidate = previous + edge.weight();
+            let index = k * n + edge.target().index();
+            if candidate < dist[index] {
+                dist[index] = candidate;
+                edge_to[index] = Some(edge.id());
+            }
+        }
+    }
+
+    // Karp: minimum mean = min over v of max over k of (dist[n][v] - dist[k][v]) / (n - k)
+    let mut best: Option<(f64, usize)> = None;
+    for v in 0..n {
+        let walk_weight = dist[n * n + v];
+        if walk_weight == f64::INFINITY {
+            continue;
+        }
+        let mut worst_mean = f64::NEG_INFINITY;
+        for k in 0..n {
+            let prefix_weight = dist[k * n + v];
+            if prefix_weight != f64::INFINITY {
+                worst_mean = worst_mean.max((walk_weight - prefix_weight) / (n - k) as f64);
+            }
+        }
+        if best.is_none_or(|(best_mean, _)| worst_mean < best_mean) {
+            best = Some((worst_mean, v));
+        }
+    }
+    // No walk of n edges means no cycle
+    let (_, best_node) = best?;
+
+    // The n edge walk ending at best_node repeats a node, and any cycle on it has the minimum mean.
+    // Walk it backwards until a node repeats, then the edges since its last visit are the cycle.
+    let mut visited_at: Vec<Option<usize>> = vec![None; n];
+    let mut walk_edges: Vec<EdgeIndex> = Vec::new();
+    let mut node = NodeIndex::new(best_node);
+    visited_at[node.index()] = Some(0);
+    for k in (1..=n).rev() {
+        let edge = edge_to[k * n + node.index()].unwrap();
+        walk_edges.push(edge);
+        node = graph.edge_endpoints(edge).unwrap().0;
+        if let Some(position) = visited_at[node.index()] {
+            let mut cycle: Path<N> = Path::new(node);
+            for edge in walk_edges[position..].iter().rev() {
+                cycle.add_to_path(graph, *edge);
+            }
+            return Some(cycle);
+        }
+        visited_at[node.index()] = Some(walk_edges.len());
+    }
+    unreachable!("a walk of V edges must repeat a node")
+}
+
+// Largest graph get_min_mean_cycle() runs on, its walk tables take about 64 MiB at this size.
+// A budget can't stop the allocation, which happens before the first relaxation.
+pub const MIN_MEAN_CYCLE_MAX_NODES: usize = 2048;
+
+// Modified queue-based Bellman-Ford algorithm. Only difference with get_negative_cycle_for_source_quick is that we collect every cycle in the parent pointers once one is found.
+// Parent pointers give each node at most one incoming tree edge, so these cycles are node-disjoint and there are at most V / 2 of them.
+// Only cycles lighter than -epsilon, see _take_parent_cycles() for cycles that aren't.
+pub fn get_all_negative_cycles_for_source<N>(
+    graph: &Graph<N, f64>,
+    source: NodeIndex,
+    epsilon: f64,
+) -> Vec<Path<N>> {
+    with_scratch(&SPFA_SCRATCH, |scratch| {
+        scratch.reset(graph.node_count());
+        // Counter of relax operations
+        let mut counter = 0;
 
-                // Check for cycle every V times we call relax.
-                if counter % graph.node_count() == 0 {
-                    // Construct current SPT from edgeTo collection
-                    let mut spt: Graph<N, f64> = Graph::new();
+        scratch.queue.push_back(source);
+        scratch.on_queue[source.index()] = true;
+        scratch.dist[source.index()] = 0.0;
 
-                    // Zzz need to implement N: Clone trait just for this one line
-                    // I guess the issue is that `graph` owns N, but to make another graph with the same nodes we need to `copy` the nodes over. So we need to tell the comiler that N is a type that can be safely deep cloned.
-                    for node in graph.node_indices() {
-                        spt.add_node(graph.node_weight(node).unwrap().clone());
+        while let Some(current_node) = scratch.queue.pop_front() {
+            scratch.on_queue[current_node.index()] = false;
+
+            // Iterate through all neighbours
+            for edge in graph.edges_directed(current_node, Outgoing) {
+                let weight = edge.weight();
+                let target_node = edge.target();
+
+                // Relax operation
+                if scratch.dist[target_node.index()]
+                    > scratch.dist[current_node.index()] + weight + RELAX_TOLERANCE
+                    && !scratch.banned.contains(&edge.id())
+                {
+                    scratch.dist[target_node.index()] = scratch.dist[current_node.index()] + weight;
+                    scratch.edge_to[target_node.index()] = Some(edge.id());
+                    counter += 1;
+
+                    if !scratch.on_queue[target_node.index()] {
+                        scratch.queue.push_back(target_node);
+                        scratch.on_queue[target_node.index()] = true;
                     }
 
-                    for node in graph.node_indices() {
-                        if let Some(spt_edge) = edgeTo.get(&node).unwrap() {
-                            let spt_edge_weight = spt_edge.weight();
-                            let spt_edge_source = spt_edge.source();
-                            spt.add_edge(spt_edge_source, node, *spt_edge_weight);
+                    // Check for cycle every V times we call relax.
+                    if counter % graph.node_count() == 0 {
+                        let cycles = _take_parent_cycles(graph, scratch, epsilon, &|_| true, true);
+                        if !cycles.is_empty() {
+                            return cycles;
                         }
                     }
+                }
+            }
+        }
 
-                    let (cycle_found, spt_cycle) = has_cycle(&spt);
-                    if cycle_found {
-                        return find_cycles(&spt);
-                    }
+        // Emptied queue without finding cycle
+        vec![]
+    })
+}
+
+// The k most negative cycles, sorted from most negative to least. Same result as the first k of get_all_negative_cycles_bounded().
+// Keeps the best k found so far in a heap, and prunes any branch that can't beat the k-th best
+// even if every remaining hop were the most negative edge in the graph. Until we have k, that is any branch that can't go negative.
+pub fn get_top_k_negative_cycles<N>(
+    graph: &Graph<N, f64>,
+    k: usize,
+    max_hops: Option<usize>,
+) -> Vec<Path<N>> {
+    get_top_k_negative_cycles_with_budget(graph, k, max_hops, 0.0, &|_| true, &Budget::unlimited())
+}
+
+// Same as get_top_k_negative_cycles(), but only cycles lighter than -epsilon, and gives up once `budget` runs out, returning the best k found so far.
+// Only cycles `accept` agrees to count towards the k, so one it turns down never tightens the bound.
+pub fn get_top_k_negative_cycles_with_budget<N>(
+    graph: &Graph<N, f64>,
+    k: usize,
+    max_hops: Option<usize>,
+    epsilon: f64,
+    accept: &dyn Fn(&Path<N>) -> bool,
+    budget: &Budget,
+) -> Vec<Path<N>> {
+    if k == 0 {
+        return vec![];
+    }
+
+    // Max-heap on weight, so the k-th best is on top and the first to go
+    let mut best: BinaryHeap<Path<N>> = BinaryHeap::with_capacity(k + 1);
+    let bound = Cell::new(-epsilon);
+    _find_cycles_from(
+        graph,
+        graph.node_indices().rev(),
+        max_hops,
+        &bound,
+        budget,
+        &mut |cycle| {
+            if cycle.weight() < bound.get() && accept(&cycle) {
+                best.push(cycle);
+                if best.len() > k {
+                    best.pop();
+                }
+                if best.len() == k {
+                    bound.set(best.peek().unwrap().weight());
                 }
             }
+            true
+        },
+    );
+    best.into_sorted_vec()
+}
+
+// Negative cycles through `node`, each starting and ending at `node`, sorted from most negative to least.
+// Only cycles of at most `max_hops` edges if given.
+pub fn get_all_negative_cycles_through_node<N>(
+    graph: &Graph<N, f64>,
+    node: NodeIndex,
+    max_hops: Option<usize>,
+) -> Vec<Path<N>> {
+    get_all_negative_cycles_through_node_with_budget(
+        graph,
+        node,
+        max_hops,
+        0.0,
+        &Budget::unlimited(),
+    )
+}
+
+// Same as get_all_negative_cycles_through_node(), but only cycles lighter than -epsilon, and gives up once `budget` runs out, returning the cycles found so far.
+pub fn get_all_negative_cycles_through_node_with_budget<N>(
+    graph: &Graph<N, f64>,
+    node: NodeIndex,
+    max_hops: Option<usize>,
+    epsilon: f64,
+    budget: &Budget,
+) -> Vec<Path<N>> {
+    // No negative cycle reachable from node means none passes through it, so skip the enumeration.
+    // Any negative cycle will do here, one that isn't lighter than -epsilon may still share nodes with one that is
+    let (negative_cycle_found, _) =
+        _get_negative_cycle_for_source_quick(graph, node, 0.0, &|_| true, budget);
+    if !negative_cycle_found {
+        return vec![];
+    }
+
+    let mut negative_paths: Vec<Path<N>> = Vec::new();
+    _find_cycles_through_node(graph, node, max_hops, budget, &mut |cycle| {
+        if cycle.weight() < -epsilon {
+            negative_paths.push(cycle);
         }
+        true
+    });
+    negative_paths.sort_unstable();
+    negative_paths
+}
+
+// First negative cycle found through `node`, starting and ending at `node`.
+// Only cycles of at most `max_hops` edges if given.
+pub fn get_negative_cycle_through_node_quick<N>(
+    graph: &Graph<N, f64>,
+    node: NodeIndex,
+    max_hops: Option<usize>,
+) -> Option<Path<N>> {
+    get_negative_cycle_through_node_quick_with_budget(
+        graph,
+        node,
+        max_hops,
+        0.0,
+        &|_| true,
+        &Budget::unlimited(),
+    )
+}
+
+// Same as get_negative_cycle_through_node_quick(), but only a cycle lighter than -epsilon that `accept` agrees to, and gives up once `budget` runs out.
+pub fn get_negative_cycle_through_node_quick_with_budget<N>(
+    graph: &Graph<N, f64>,
+    node: NodeIndex,
+    max_hops: Option<usize>,
+    epsilon: f64,
+    accept: &dyn Fn(&Path<N>) -> bool,
+    budget: &Budget,
+) -> Option<Path<N>> {
+    let (_, cycle) = _get_negative_cycle_for_source_quick(graph, node, epsilon, accept, budget);
+    // No negative cycle reachable from node means none passes through it
+    let mut cycle = cycle?;
+    if cycle.rotate_to(node) && cycle.edges().len() <= max_hops.unwrap_or(usize::MAX) {
+        return Some(cycle);
     }
 
-    // Emptied queue without finding cycle
-    vec![]
+    // Bellman-Ford found a cycle elsewhere or too long, enumerate cycles through node until a negative one turns up
+    let mut negative_path: Option<Path<N>> = None;
+    _find_cycles_through_node(graph, node, max_hops, budget, &mut |cycle| {
+        if cycle.weight() < -epsilon && accept(&cycle) {
+            negative_path = Some(cycle);
+            return false;
+        }
+        true
+    });
+    negative_path
 }
 
 // Modified queue-based Bellman-Ford algorithm. O(E) practically, O(V * E) theoretically.
-pub fn get_negative_cycle_for_source_quick<N: Clone>(
+pub fn get_negative_cycle_for_source_quick<N>(
     graph: &Graph<N, f64>,
     source: NodeIndex,
 ) -> (bool, Option<Path<N>>) {
-    // Node => Weight of current shortest path from source.
-    let mut dist: HashMap<NodeIndex, f64> = HashMap::new();
-    // Node => Edge in current shortest path with node as target_node.
-    let mut edgeTo: HashMap<NodeIndex, Option<EdgeReference<f64>>> = HashMap::new();
-    let mut queue: VecDeque<NodeIndex> = VecDeque::new();
-    let mut on_queue: HashMap<NodeIndex, bool> = HashMap::new();
-    // Counter of relax operations
-    let mut counter = 0;
+    _get_negative_cycle_for_source_quick(graph, source, 0.0, &|_| true, &Budget::unlimited())
+}
 
-    for node in graph.node_indices() {
-        edgeTo.insert(node, None);
-        dist.insert(node, f64::MAX);
-        on_queue.insert(node, false);
-    }
+// get_negative_cycle_for_source_quick(), only for a cycle lighter than -epsilon that `accept` agrees to, see _take_parent_cycles() for cycles that aren't,
+// and giving up without a cycle once `budget` runs out, e.g. cancelled by another thread that found one
+fn _get_negative_cycle_for_source_quick<N>(
+    graph: &Graph<N, f64>,
+    source: NodeIndex,
+    epsilon: f64,
+    accept: &dyn Fn(&Path<N>) -> bool,
+    budget: &Budget,
+) -> (bool, Option<Path<N>>) {
+    with_scratch(&SPFA_SCRATCH, |scratch| {
+        scratch.reset(graph.node_count());
+        // Counter of relax operations
+        let mut counter = 0;
 
-    queue.push_back(source);
-    on_queue.insert(source, true);
-    dist.insert(source, 0.0);
+        scratch.queue.push_back(source);
+        scratch.on_queue[source.index()] = true;
+        scratch.dist[source.index()] = 0.0;
 
-    while !queue.is_empty() {
-        let current_node = queue.pop_front().unwrap();
-        on_queue.insert(source, false);
+        while let Some(current_node) = scratch.queue.pop_front() {
+            scratch.on_queue[current_node.index()] = false;
 
-        // Iterate through all neighbours
-        for edge in graph.edges_directed(current_node, Outgoing) {
-            let weight = edge.weight();
-            let target_node = edge.target();
+            // Iterate through all neighbours
+            for edge in graph.edges_directed(current_node, Outgoing) {
+                if !budget.spend() {
+                    return (false, None);
+                }
+                let weight = edge.weight();
+                let target_node = edge.target();
 
-            // Relax operation
-            if *dist.get(&target_node).unwrap() > dist.get(&current_node).unwrap() + weight {
-                *dist.get_mut(&target_node).unwrap() = dist.get(&current_node).unwrap() + weight;
-                *edgeTo.get_mut(&target_node).unwrap() = Some(edge);
+                // Relax operation
+                if scratch.dist[target_node.index()]
+                    > scratch.dist[current_node.index()] + weight + RELAX_TOLERANCE
+                    && !scratch.banned.contains(&edge.id())
+                {
+                    scratch.dist[target_node.index()] = scratch.dist[current_node.index()] + weight;
+                    scratch.edge_to[target_node.index()] = Some(edge.id());
+                    counter += 1;
+
+                    if !scratch.on_queue[target_node.index()] {
+                        scratch.queue.push_back(target_node);
+                        scratch.on_queue[target_node.index()] = true;
+                    }
+
+                    // Check for cycle every V times we call relax.
+                    if counter % graph.node_count() == 0 {
+                        if let Some(cycle) =
+                            _take_parent_cycles(graph, scratch, epsilon, accept, false).pop()
+                        {
+                            return (true, Some(cycle));
+                        }
+                    }
+                }
+            }
+        }
+
+        // Emptied queue without finding cycle
+        (false, None)
+    })
+}
+
+// Incremental queue-based Bellman-Ford, for rescanning after a few edges changed.
+// `potentials` are distance labels from a virtual source with a 0.0 edge to every node, left behind by the previous call.
+// Labels are feasible (dist[target] <= dist[source] + weight for every edge) once a call returns None, and stay feasible when weights only go up,
+// so only edges in `touched_edges` (added or made cheaper since) can violate them. We start relaxing from those edges only,
+// so the cost is proportional to the region affected by the change rather than the whole graph.
+// Any negative cycle created by the change must pass through a touched edge, otherwise the previous call would have found it.
+// Pass empty `potentials` and every edge as touched for a full scan.
+// Returns a negative cycle if one is found, in which case `potentials` are no longer feasible and should not be reused.
+// Only cycles lighter than -epsilon are returned. Passing over any other negative cycle leaves the labels infeasible around it,
+// so `potentials` are cleared and the next call should be a full scan.
+pub fn get_negative_cycle_incremental<N>(
+    graph: &Graph<N, f64>,
+    potentials: &mut Vec<f64>,
+    touched_edges: &[EdgeIndex],
+    epsilon: f64,
+) -> Option<Path<N>> {
+    get_negative_cycle_incremental_with_budget(
+        graph,
+        potentials,
+        touched_edges,
+        epsilon,
+        &|_| true,
+        &Budget::unlimited(),
+    )
+}
+
+// Same as get_negative_cycle_incremental(), but gives up once `budget` runs out, returning a cycle among the parent pointers at that point if any.
+// Labels are left infeasible wherever the search stopped, so `potentials` are cleared and the next call should be a full scan.
+// Cycles `accept` turns down are passed over like those not lighter than -epsilon.
+pub fn get_negative_cycle_incremental_with_budget<N>(
+    graph: &Graph<N, f64>,
+    potentials: &mut Vec<f64>,
+    touched_edges: &[EdgeIndex],
+    epsilon: f64,
+    accept: &dyn Fn(&Path<N>) -> bool,
+    budget: &Budget,
+) -> Option<Path<N>> {
+    // Nodes added since the previous call start at the virtual source's distance
+    potentials.resize(graph.node_count(), 0.0);
+
+    with_scratch(&SPFA_SCRATCH, |scratch| {
+        // edge_to is only set for nodes relaxed in this call, dist is unused since potentials take its place
+        scratch.reset(graph.node_count());
+        // Counter of relax operations
+        let mut counter = 0;
+
+        // Seed queue with targets of touched edges that violate the current labels
+        for edge in touched_edges {
+            if !budget.spend() {
+                break;
+            }
+            let (source_node, target_node) = graph.edge_endpoints(*edge).unwrap();
+            let candidate = potentials[source_node.index()] + graph.edge_weight(*edge).unwrap();
+            if potentials[target_node.index()] > candidate + RELAX_TOLERANCE {
+                potentials[target_node.index()] = candidate;
+                scratch.edge_to[target_node.index()] = Some(*edge);
                 counter += 1;
 
-                if !on_queue.get(&target_node).unwrap() {
-                    queue.push_back(target_node);
-                    on_queue.insert(target_node, true);
+                if !scratch.on_queue[target_node.index()] {
+                    scratch.queue.push_back(target_node);
+                    scratch.on_queue[target_node.index()] = true;
                 }
+            }
+        }
 
-                // Check for cycle every V times we call relax.
-                if counter % graph.node_count() == 0 {
-                    // Construct current SPT from edgeTo collection
-                    let mut spt: Graph<N, f64> = Graph::new();
+        'search: while let Some(current_node) = scratch.queue.pop_front() {
+            scratch.on_queue[current_node.index()] = false;
 
-                    // Zzz need to implement N: Clone trait just for this one line
-                    // I guess the issue is that `graph` owns N, but to make another graph with the same nodes we need to `copy` the nodes over. So we need to tell the comiler that N is a type that can be safely deep cloned.
-                    for node in graph.node_indices() {
-                        spt.add_node(graph.node_weight(node).unwrap().clone());
+            for edge in graph.edges_directed(current_node, Outgoing) {
+                if !budget.spend() {
+                    break 'search;
+                }
+                let target_node = edge.target();
+                let candidate = potentials[current_node.index()] + edge.weight();
+
+                // Relax operation
+                if potentials[target_node.index()] > candidate + RELAX_TOLERANCE
+                    && !scratch.banned.contains(&edge.id())
+                {
+                    potentials[target_node.index()] = candidate;
+                    scratch.edge_to[target_node.index()] = Some(edge.id());
+                    counter += 1;
+
+                    if !scratch.on_queue[target_node.index()] {
+                        scratch.queue.push_back(target_node);
+                        scratch.on_queue[target_node.index()] = true;
                     }
 
-                    for node in graph.node_indices() {
-                        if let Some(spt_edge) = edgeTo.get(&node).unwrap() {
-                            let spt_edge_weight = spt_edge.weight();
-                            let spt_edge_source = spt_edge.source();
-                            spt.add_edge(spt_edge_source, node, *spt_edge_weight);
+                    // Check for cycle every V times we call relax.
+                    if counter % graph.node_count() == 0 {
+                        if let Some(cycle) =
+                            _take_parent_cycles(graph, scratch, epsilon, accept, false).pop()
+                        {
+                            return Some(cycle);
                         }
                     }
+                }
+            }
+        }
 
-                    let (cycle_found, spt_cycle) = has_cycle(&spt);
+        // Ran out of budget, settle for a cycle found so far
+        if budget.is_exhausted() {
+            potentials.clear();
+            return _take_parent_cycles(graph, scratch, epsilon, accept, false).pop();
+        }
 
-                    if cycle_found {
-                        return (cycle_found, spt_cycle);
-                    }
+        // Emptied queue without finding cycle
+        if !scratch.banned.is_empty() {
+            potentials.clear();
+        }
+        None
+    })
+}
+
+// Relaxations must improve a distance by more than this, so rounding noise in -ln(rate) sums can't keep relaxing around a cycle.
+// A cycle of k edges lighter than -k * RELAX_TOLERANCE still relaxes, whatever the epsilon of the search.
+const RELAX_TOLERANCE: f64 = 1e-12;
+
+// Cycles among the parent pointers lighter than -epsilon that `accept` agrees to, only the first unless `all`, see _visit_parent_cycles().
+// Any other parent pointer cycle is negative but not profitable enough, and would keep relaxing around forever.
+// Its edges, and any parallel edges, are banned from relaxing again, so a cycle sharing a pair of nodes with it may be missed.
+fn _take_parent_cycles<N>(
+    graph: &Graph<N, f64>,
+    scratch: &mut SpfaScratch,
+    epsilon: f64,
+    accept: &dyn Fn(&Path<N>) -> bool,
+    all: bool,
+) -> Vec<Path<N>> {
+    let mut cycles: Vec<Path<N>> = Vec::new();
+    let banned = &mut scratch.banned;
+    _visit_parent_cycles(
+        graph,
+        &scratch.edge_to,
+        &mut scratch.walk_ids,
+        &mut |cycle| {
+            if cycle.weight() < -epsilon && accept(&cycle) {
+                cycles.push(cycle);
+                return all;
+            }
+            for edge in cycle.edges() {
+                let (source, target) = graph.edge_endpoints(edge).unwrap();
+                banned.extend(graph.edges_connecting(source, target).map(|edge| edge.id()));
+            }
+            true
+        },
+    );
+    cycles
+}
+
+// Find cycles by walking the parent pointers of a shortest path tree, O(V).
+// Any cycle among the parent pointers left by Bellman-Ford relaxations is a negative cycle, and edges are the original graph's.
+// Each cycle is handed to `visitor`, stops early once `visitor` returns false.
+// `walk_ids` is working memory, overwritten on every call.
+fn _visit_parent_cycles<N>(
+    graph: &Graph<N, f64>,
+    edgeTo: &[Option<EdgeIndex>],
+    walk_ids: &mut Vec<usize>,
+    visitor: &mut dyn FnMut(Path<N>) -> bool,
+) {
+    // Node => id of the walk that first reached it, 0 if not yet reached
+    walk_ids.clear();
+    walk_ids.resize(edgeTo.len(), 0);
+
+    for walk_start in 0..edgeTo.len() {
+        if walk_ids[walk_start] != 0 {
+            continue;
+        }
+
+        let walk_id = walk_start + 1;
+        let mut node = walk_start;
+        loop {
+            walk_ids[node] = walk_id;
+            let parent = match edgeTo[node] {
+                None => break,
+                Some(edge) => graph.edge_endpoints(edge).unwrap().0.index(),
+            };
+
+            // Reached a node from an earlier walk, which has no cycle beyond it
+            if walk_ids[parent] != 0 && walk_ids[parent] != walk_id {
+                break;
+            }
+            // Reached a node from this walk, so we have gone around a cycle.
+            // Every node on it now has this walk's id, so later walks stop when they reach it.
+            if walk_ids[parent] == walk_id {
+                if !visitor(_parent_cycle_to_path(graph, edgeTo, NodeIndex::new(parent))) {
+                    return;
                 }
+                break;
             }
+            node = parent;
         }
     }
+}
 
-    // Emptied queue without finding cycle
-    (false, None)
+// Path around the parent pointer cycle containing `cycle_node`, in trading order
+fn _parent_cycle_to_path<N>(
+    graph: &Graph<N, f64>,
+    edgeTo: &[Option<EdgeIndex>],
+    cycle_node: NodeIndex,
+) -> Path<N> {
+    // Parent pointers go backwards, so collect the cycle edges and then reverse them
+    let mut edgeStack: Vec<EdgeIndex> = Vec::new();
+    let mut node = cycle_node;
+    loop {
+        let edge = edgeTo[node.index()].unwrap();
+        edgeStack.push(edge);
+        node = graph.edge_endpoints(edge).unwrap().0;
+        if node == cycle_node {
+            break;
+        }
+    }
+
+    let mut cycle: Path<N> = Path::new(cycle_node);
+    while let Some(edge) = edgeStack.pop() {
+        cycle.add_to_path(graph, _get_best_parallel_edge(graph, edge));
+    }
+    cycle
+}
+
+// Lowest weight edge between the same two nodes as `edge`, which may be `edge` itself.
+// upsert_path keeps two edges (pools) per pair, and the parent pointers may have settled on the worse one.
+fn _get_best_parallel_edge<N>(graph: &Graph<N, f64>, edge: EdgeIndex) -> EdgeIndex {
+    let (source, target) = graph.edge_endpoints(edge).unwrap();
+    graph
+        .edges_connecting(source, target)
+        .min_by(|a, b| a.weight().total_cmp(b.weight()))
+        .unwrap()
+        .id()
 }
 
 // DFS algorithm to determine if a cycle exists in a graph, linear time algorithm: O(V + E).
 // Returns tuple
 // tuple.0 (bool): false if no cycle found, true if cycle present.
 // tuple.1 (Option<Path<N>>): None if no cycle found, Path representing cycle if cycle found.
-pub fn has_cycle<N: Clone>(graph: &Graph<N, f64>) -> (bool, Option<Path<N>>) {
-    // Initialise data structures
-    let mut visited: HashMap<NodeIndex, bool> = HashMap::new();
-    let mut edgeTo: HashMap<NodeIndex, Option<EdgeIndex>> = HashMap::new();
-    let mut onStack: HashMap<NodeIndex, bool> = HashMap::new();
-    let mut cycle: Option<Path<N>> = None;
+pub fn has_cycle<N>(graph: &Graph<N, f64>) -> (bool, Option<Path<N>>) {
+    with_scratch(&DFS_SCRATCH, |scratch| {
+        // Initialise data structures
+        scratch.reset(graph.node_count());
+        let mut cycle: Option<Path<N>> = None;
 
-    for node in graph.node_indices() {
-        visited.insert(node, false);
-        edgeTo.insert(node, None);
-        onStack.insert(node, false);
-    }
-
-    for node in graph.node_indices() {
-        if !visited.get(&node).unwrap() {
-            _has_cycle_dfs(
-                graph,
-                node,
-                &mut visited,
-                &mut edgeTo,
-                &mut onStack,
-                &mut cycle,
-            );
+        for node in graph.node_indices() {
+            if !scratch.visited[node.index()] {
+                _has_cycle_dfs(graph, node, scratch, &mut cycle);
+            }
         }
-    }
 
-    match cycle {
-        None => (false, None),
-        Some(discovered_cycle) => (true, Some(discovered_cycle)),
-    }
+        match cycle {
+            None => (false, None),
+            Some(discovered_cycle) => (true, Some(discovered_cycle)),
+        }
+    })
 }
 
-fn _has_cycle_dfs<N: Clone>(
+fn _has_cycle_dfs<N>(
     graph: &Graph<N, f64>,
     node: NodeIndex,
-    visited: &mut HashMap<NodeIndex, bool>,
-    edgeTo: &mut HashMap<NodeIndex, Option<EdgeIndex>>,
-    onStack: &mut HashMap<NodeIndex, bool>,
+    scratch: &mut DfsScratch,
     cycle: &mut Option<Path<N>>,
 ) {
-    onStack.insert(node, true);
-    visited.insert(node, true);
+    scratch.on_stack[node.index()] = true;
+    scratch.visited[node.index()] = true;
 
     for edge in graph.edges_directed(node, Outgoing) {
         let edgeId = edge.id();
@@ -261,11 +928,11 @@ fn _has_cycle_dfs<N: Clone>(
 
         if cycle.is_some() {
             return;
-        } else if !visited.get(&target).unwrap() {
-            edgeTo.insert(target, Some(edgeId));
-            _has_cycle_dfs(graph, target, visited, edgeTo, onStack, cycle);
+        } else if !scratch.visited[target.index()] {
+            scratch.edge_to[target.index()] = Some(edgeId);
+            _has_cycle_dfs(graph, target, scratch, cycle);
         // If target node is on stack, we have found a cycle
-        } else if *onStack.get(&target).unwrap() {
+        } else if scratch.on_stack[target.index()] {
             // We have an issue that edgeTo gives EdgeIndex type, whereas we need type N for Path
             // Also Path needs to start from the first node, whereas we can only get our cycles backwards by unpopping the stack.
             let mut new_cycle: Path<N> = Path::new(target);
@@ -280,206 +947,290 @@ fn _has_cycle_dfs<N: Clone>(
                     break;
                 }
 
-                let previous_edge = edgeTo.get(&most_recent_edge_from).unwrap().unwrap();
+                let previous_edge = scratch.edge_to[most_recent_edge_from.index()].unwrap();
                 edgeStack.push(previous_edge);
             }
 
-            while !edgeStack.is_empty() {
-                new_cycle.add_to_path(graph, edgeStack.pop().unwrap());
+            while let Some(edge) = edgeStack.pop() {
+                new_cycle.add_to_path(graph, _get_best_parallel_edge(graph, edge));
             }
 
             *cycle = Some(new_cycle);
         }
     }
 
-    onStack.insert(node, false);
+    scratch.on_stack[node.index()] = false;
 }
 
 // Intuitively this is O((E + V) * C), since it's a DFS-style approach and it 'unwinds' whenever a cycle is found.
 // In our application, our initial graph will be a complete graph hence we will waste a lot of 'C's on noise.
 // So we hope to filter our graph down using modified Bellman-Ford prior to using this function.
-pub fn find_cycles<N: Clone>(graph: &Graph<N, f64>) -> Vec<Path<N>> {
+pub fn find_cycles<N>(graph: &Graph<N, f64>) -> Vec<Path<N>> {
     // Collections of cycles
     let mut cycles: Vec<Path<N>> = Vec::new();
-    // Node => isBlocked
-    let mut blocked: HashMap<NodeIndex, bool> = HashMap::new();
-    // source_node => edges, represented by vector of destination_nodes
-    let mut edges: HashMap<NodeIndex, Vec<NodeIndex>> = HashMap::new();
-    // source_node => blocked edges, represented by destination node?
-    let mut blocked_edges: HashMap<NodeIndex, HashSet<NodeIndex>> = HashMap::new();
-    // Unsure of what we use this stack for
-    let mut stack: Vec<NodeIndex> = Vec::new();
-    // Set to keep track of which nodes we have call circuit for
-    let mut circuited_nodes: HashSet<NodeIndex> = HashSet::new();
-    // Collection of all nodes we have yet to call circuit on
-    let mut uncircuited_nodes: Vec<NodeIndex> = Vec::new();
-
-    // Initialize data structures
-    for node in graph.node_indices() {
-        blocked.insert(node, false);
-        blocked_edges.insert(node, HashSet::new());
-        uncircuited_nodes.push(node);
+    find_cycles_with_visitor(graph, None, &mut |cycle| {
+        cycles.push(cycle);
+        true
+    });
+    cycles
+}
 
-        let mut neighbors: Vec<NodeIndex> = Vec::new();
-        for target_node in graph.neighbors(node) {
-            neighbors.push(target_node);
-        }
-        edges.insert(node, neighbors);
-    }
+// Same search as find_cycles(), but hands each cycle to `visitor` as soon as it is found instead of collecting them.
+// Search stops early once `visitor` returns false.
+// With `max_hops`, only cycles of at most `max_hops` edges are visited, and the search never goes deeper than that.
+pub fn find_cycles_with_visitor<N>(
+    graph: &Graph<N, f64>,
+    max_hops: Option<usize>,
+    visitor: &mut dyn FnMut(Path<N>) -> bool,
+) {
+    find_cycles_with_budget(graph, max_hops, &Budget::unlimited(), visitor);
+}
 
-    while !uncircuited_nodes.is_empty() {
-        let start: NodeIndex = uncircuited_nodes.pop().unwrap();
+// Same search as find_cycles_with_visitor(), but stops once `budget` runs out.
+pub fn find_cycles_with_budget<N>(
+    graph: &Graph<N, f64>,
+    max_hops: Option<usize>,
+    budget: &Budget,
+    visitor: &mut dyn FnMut(Path<N>) -> bool,
+) {
+    // Start from the last node first, circuiting every node once
+    let bound = Cell::new(f64::INFINITY);
+    _find_cycles_from(
+        graph,
+        graph.node_indices().rev(),
+        max_hops,
+        &bound,
+        budget,
+        visitor,
+    );
+}
 
-        // Reset all blocked markers for nodes and edges
+// Same search as find_cycles_with_visitor(), but only cycles through `node`, each starting and ending at `node`.
+pub fn find_cycles_through_node_with_visitor<N>(
+    graph: &Graph<N, f64>,
+    node: NodeIndex,
+    max_hops: Option<usize>,
+    visitor: &mut dyn FnMut(Path<N>) -> bool,
+) {
+    _find_cycles_through_node(graph, node, max_hops, &Budget::unlimited(), visitor);
+}
+
+fn _find_cycles_through_node<N>(
+    graph: &Graph<N, f64>,
+    node: NodeIndex,
+    max_hops: Option<usize>,
+    budget: &Budget,
+    visitor: &mut dyn FnMut(Path<N>) -> bool,
+) {
+    let bound = Cell::new(f64::INFINITY);
+    _find_cycles_from(
+        graph,
+        std::iter::once(node),
+        max_hops,
+        &bound,
+        budget,
+        visitor,
+    );
+}
+
+// Circuit search from each of `starts` in turn, later starts skip cycles through earlier ones since those were already found.
+// Branches that can't lead to a cycle lighter than `bound` are pruned, pass f64::INFINITY to visit every cycle.
+// Every edge examined spends from `budget`, and the search stops once it runs out.
+fn _find_cycles_from<N>(
+    graph: &Graph<N, f64>,
+    starts: impl Iterator<Item = NodeIndex>,
+    max_hops: Option<usize>,
+    bound: &Cell<f64>,
+    budget: &Budget,
+    visitor: &mut dyn FnMut(Path<N>) -> bool,
+) {
+    with_scratch(&CIRCUIT_SCRATCH, |scratch| {
+        // An elementary cycle can't have more edges than there are nodes
+        let max_hops = max_hops.unwrap_or(usize::MAX).min(graph.node_count());
+        // Every cycle has at least one edge
+        if max_hops == 0 {
+            return;
+        }
+
+        // Initialize data structures
+        scratch.reset(graph.node_count());
         for node in graph.node_indices() {
-            blocked.insert(node, false);
-            blocked_edges.get_mut(&node).unwrap().clear();
+            // One entry per neighbor, through the lowest weight of any parallel edges.
+            // A cycle through the other pool can only be worse, and would otherwise be enumerated as a separate cycle.
+            let neighbors = &mut scratch.edges[node.index()];
+            for edge in graph.edges(node) {
+                match scratch.neighbor_positions[edge.target().index()] {
+                    Some(position) => {
+                        if edge.weight() < graph.edge_weight(neighbors[position].1).unwrap() {
+                            neighbors[position].1 = edge.id();
+                        }
+                    }
+                    None => {
+                        scratch.neighbor_positions[edge.target().index()] = Some(neighbors.len());
+                        neighbors.push((edge.target(), edge.id()));
+                    }
+                }
+            }
+            for (target_node, _) in neighbors.iter() {
+                scratch.neighbor_positions[target_node.index()] = None;
+            }
         }
 
-        _find_cycles_circuit(
-            start,
-            start,
+        let mut search = CircuitSearch {
             graph,
-            &mut blocked,
-            &mut edges,
-            &mut blocked_edges,
-            &mut cycles,
-            &mut stack,
-            &mut circuited_nodes,
-        );
-        circuited_nodes.insert(start);
-    }
+            start_node: NodeIndex::end(),
+            max_hops,
+            min_edge_weight: graph
+                .edge_weights()
+                .fold(0.0, |min, weight| weight.min(min)),
+            bound,
+            budget,
+            visitor,
+            stopped: false,
+        };
 
-    cycles
+        for start in starts {
+            if search.stopped {
+                break;
+            }
+
+            scratch.unblock_all();
+            search.start_node = start;
+            _find_cycles_circuit(start, 0.0, &mut search, scratch);
+            scratch.circuited[start.index()] = true;
+        }
+    })
+}
+
+// Running path weights are summed in search order, but cycle weights from their canonical start, so the two can differ by rounding.
+// Prune with this much slack, so a cycle that only just beats bound isn't lost to rounding.
+const PRUNE_SLACK: f64 = 1e-9;
+
+// State shared by every level of one circuit search
+struct CircuitSearch<'a, N> {
+    graph: &'a Graph<N, f64>,
+    // Node we visited in the first circuit call, bottom of the stack
+    start_node: NodeIndex,
+    // Longest cycle we are looking for, in edges
+    max_hops: usize,
+    // Most negative edge weight in the graph, 0.0 if there are none
+    min_edge_weight: f64,
+    // Only cycles lighter than this are wanted, may be lowered by visitor as the search goes
+    bound: &'a Cell<f64>,
+    budget: &'a Budget<'a>,
+    visitor: &'a mut dyn FnMut(Path<N>) -> bool,
+    // Set once visitor asks us to stop, or budget runs out
+    stopped: bool,
 }
 
-fn _find_cycles_circuit<N: Clone>(
+fn _find_cycles_circuit<N>(
     // Node we are currently visiting with circuit
     circuit_node: NodeIndex,
-    // Node we visited in the first circuit call (should be bottom of stack?)
-    start_node: NodeIndex,
-    graph: &Graph<N, f64>,
-    blocked: &mut HashMap<NodeIndex, bool>,
-    edges: &HashMap<NodeIndex, Vec<NodeIndex>>,
-    blocked_edges: &mut HashMap<NodeIndex, HashSet<NodeIndex>>,
-    cycles: &mut Vec<Path<N>>,
-    stack: &mut Vec<NodeIndex>,
-    circuited_nodes: &mut HashSet<NodeIndex>,
+    // Weight of the path from start_node to circuit_node
+    path_weight: f64,
+    search: &mut CircuitSearch<'_, N>,
+    scratch: &mut CircuitScratch,
 ) -> bool {
     let mut is_circuit_found = false;
     // Keeping track of what is on the recursion stack.
-    stack.push(circuit_node);
+    scratch.stack.push(circuit_node);
     // Not only do we keep track on the recursion stack, but also put a temporary 'blocked' marker on it? Not a permanent 'visited' marker.
-    blocked.insert(circuit_node, true);
+    scratch.blocked[circuit_node.index()] = true;
+
+    // Iterate through every edge with node == source, by position since the recursion below needs scratch mutably
+    for neighbor in 0..scratch.edges[circuit_node.index()].len() {
+        let (target_node, edge) = scratch.edges[circuit_node.index()][neighbor];
+
+        // Visitor has seen enough, unwind without searching further
+        if search.stopped {
+            break;
+        }
+        if !search.budget.spend() {
+            search.stopped = true;
+            break;
+        }
 
-    // Iterate through every edge with node == source,
-    for target_node in edges.get(&circuit_node).unwrap() {
         // If we have already invoked circuit for this node, skip
-        if circuited_nodes.contains(target_node) {
+        if scratch.circuited[target_node.index()] {
             continue;
         }
 
+        let target_weight = path_weight + search.graph.edge_weight(edge).unwrap();
+
         // We have found a circuit, if we have found our start_node again
-        // TO-DO, can we replace start_node with bottom of the stack?
-        if target_node == &start_node {
-            let mut cycle: Path<N> = Path::new(start_node);
-            for cycle_node in stack.iter() {
-                if cycle_node != &start_node {
-                    // Two issues here - 1.) Could have O(1) time here if we refactored, but it's O(e') where e' is edges connected to a instead
-                    let edge = graph
-                        .find_edge(*cycle.nodes().last().unwrap(), *cycle_node)
-                        .unwrap();
-                    cycle.add_to_path(graph, edge);
-                }
+        if target_node == search.start_node {
+            // Stack edges take us from start_node to circuit_node, and edge closes the cycle
+            let mut cycle: Path<N> = Path::new(search.start_node);
+            for cycle_edge in scratch.stack_edges.iter() {
+                cycle.add_to_path(search.graph, *cycle_edge);
             }
+            cycle.add_to_path(search.graph, edge);
 
-            // Cycle contains all nodes in the cycle, but is missing an edge.
-            let final_edge = graph
-                .find_edge(
-                    *cycle.nodes().last().unwrap(),
-                    *cycle.nodes().first().unwrap(),
-                )
-                .unwrap();
-            cycle.add_to_path(graph, final_edge);
-
-            cycles.push(cycle);
+            if !(search.visitor)(cycle) {
+                search.stopped = true;
+            }
 
             is_circuit_found = true;
-        // Else if target_node isn't blocked && recursive call of circuit on target_node returns true
-        // There is only one condition to return true, if circuit has been found
-        } else if !blocked.get(target_node).unwrap()
-            && _find_cycles_circuit(
-                *target_node,
-                start_node,
-                graph,
-                blocked,
-                edges,
-                blocked_edges,
-                cycles,
-                stack,
-                circuited_nodes,
-            )
+        } else if scratch.blocked[target_node.index()] {
+            continue;
+        // Going through target_node would need more than max_hops edges to get back to start_node,
+        // or can't get below bound even if every remaining hop were the most negative edge in the graph.
+        // Treat it as if a circuit was found, so nodes aren't left blocked just because we stopped looking.
+        // A node pruned here may still close a circuit when reached again by a shorter or cheaper route.
+        } else if scratch.stack.len() >= search.max_hops
+            || target_weight
+                + (search.max_hops - scratch.stack.len()) as f64 * search.min_edge_weight
+                >= search.bound.get() + PRUNE_SLACK
         {
             is_circuit_found = true;
+        // There is only one condition for circuit to return true, if circuit has been found
+        } else {
+            scratch.stack_edges.push(edge);
+            if _find_cycles_circuit(target_node, target_weight, search, scratch) {
+                is_circuit_found = true;
+            }
+            scratch.stack_edges.pop();
         }
     }
 
     // If we have found a circuit, unblock the node?
     if is_circuit_found {
-        _find_cycles_unblock::<N>(circuit_node, blocked, blocked_edges);
+        _find_cycles_unblock(circuit_node, scratch);
     // Iterate through every edge with node == source, again.
     } else {
-        for target_node in edges.get(&circuit_node).unwrap() {
+        for neighbor in 0..scratch.edges[circuit_node.index()].len() {
+            let (target_node, _) = scratch.edges[circuit_node.index()][neighbor];
             // Skip if we have already circuited this node.
-            if circuited_nodes.contains(target_node) {
+            if scratch.circuited[target_node.index()] {
                 continue;
             }
-            // Hmmm, but this is backwards to edges? It is indexed by target_node?
-            // But we don't use this blocked_edges collection anywhere? We don't use it in any conditional?
             // So any edge leading into the circuited node, needs to be marked as blocked.
-            if !blocked_edges
-                .get(target_node)
-                .unwrap()
-                .contains(&circuit_node)
-            {
-                blocked_edges
-                    .get_mut(target_node)
-                    .unwrap()
-                    .insert(circuit_node);
+            let blocked_edges = &mut scratch.blocked_edges[target_node.index()];
+            if !blocked_edges.contains(&circuit_node) {
+                blocked_edges.push(circuit_node);
             }
         }
     }
 
-    stack.pop();
+    scratch.stack.pop();
     is_circuit_found
 }
 
-fn _find_cycles_unblock<N>(
-    target_node: NodeIndex,
-    blocked: &mut HashMap<NodeIndex, bool>,
-    blocked_edges: &mut HashMap<NodeIndex, HashSet<NodeIndex>>,
-) {
-    blocked.insert(target_node, false);
-
-    let mut source_nodes_to_unblock: Vec<NodeIndex> = blocked_edges
-        .get(&target_node)
-        .unwrap()
-        .iter()
-        .cloned()
-        .collect();
+fn _find_cycles_unblock(target_node: NodeIndex, scratch: &mut CircuitScratch) {
+    scratch.blocked[target_node.index()] = false;
 
-    while !source_nodes_to_unblock.is_empty() {
-        let source_node = source_nodes_to_unblock.pop().unwrap();
+    // Take the list out so we can recurse while walking it, and put the emptied list back afterwards to keep its capacity
+    let mut source_nodes_to_unblock =
+        std::mem::take(&mut scratch.blocked_edges[target_node.index()]);
 
-        // Will only call recursive unblock if node is blocked
-        // Cannot call recursive unblock on itself, because we have unblocked it at the start of this function
-        // So we don't need to worry about mutating the same hashset
-        // Should be to move the hashset elements into vector, and clear the hashset (where is the one liner to do that lol)
-        // And the implementation for removeFromList() is an O(N) implementation anyway, making unblock an O(N^2) function if we ignore the recursive part. Provided that converting from hashset to vector is an O(N) operation, we have reduced it to an O(N) operation - copy whole hashset, then iterate through single loop for hashset elements
-        if *blocked.get(&source_node).unwrap() {
-            _find_cycles_unblock::<N>(source_node, blocked, blocked_edges);
+    // Will only call recursive unblock if node is blocked
+    // Cannot call recursive unblock on itself, because we have unblocked it at the start of this function
+    for source_node in source_nodes_to_unblock.iter() {
+        if scratch.blocked[source_node.index()] {
+            _find_cycles_unblock(*source_node, scratch);
         }
     }
 
-    blocked_edges.get_mut(&target_node).unwrap().clear()
+    source_nodes_to_unblock.clear();
+    scratch.blocked_edges[target_node.index()] = source_nodes_to_unblock;
 }
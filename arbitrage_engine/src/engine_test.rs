@@ -2,13 +2,39 @@
 mod tests {
     use super::super::engine::{
         find_cycles, 
+        find_cycles_with_visitor,
         get_all_negative_cycles_0, 
         get_all_negative_cycles_1,
+        get_all_negative_cycles_bounded,
+        get_all_negative_cycles_for_source,
+        get_all_negative_cycles_through_node,
+        get_all_negative_cycles_through_node_with_budget,
+        get_all_negative_cycles_with_budget,
         get_negative_cycle_for_source_quick,
+        get_negative_cycle_incremental,
+        get_negative_cycle_incremental_with_budget,
         get_negative_cycle_quick, 
+        get_negative_cycle_through_node_quick,
+        get_negative_cycle_through_node_quick_with_budget,
+        get_min_mean_cycle,
+        get_negative_cycles_from_super_source,
+        get_negative_cycles_from_super_source_with_budget,
+        get_top_k_negative_cycles,
+        get_top_k_negative_cycles_with_budget,
         has_cycle,
+        search_by_component,
+        strongly_connected_components,
     };
+    #[cfg(feature = "parallel")]
+    use super::super::engine::{
+        get_all_negative_cycles_1_parallel,
+        get_negative_cycle_quick_parallel,
+        search_by_component_parallel,
+    };
+    use super::super::budget::Budget;
+    use super::super::path::Path;
     use petgraph::{graph::Graph, prelude::NodeIndex};
+    use std::time::Duration;
     
     #[test]
     fn has_cycle_test_0() {
@@ -25,7 +51,7 @@ mod tests {
         graph.add_edge(nodes[4], nodes[1], 1.0);
         graph.add_edge(nodes[2], nodes[4], 1.0);
     
-        let (cycle_found, cycle) = has_cycle(&graph);
+        let (cycle_found, _cycle) = has_cycle(&graph);
         assert!(cycle_found);
     }
     
@@ -81,7 +107,7 @@ mod tests {
         graph.add_edge(nodes[6], nodes[0], -1.40);
         graph.add_edge(nodes[6], nodes[4], -1.25);
     
-        let (cycle_found, cycle) = has_cycle(&graph);
+        let (cycle_found, _cycle) = has_cycle(&graph);
         assert!(cycle_found);
     }
     
@@ -158,8 +184,63 @@ mod tests {
     
         let cycles = find_cycles(&graph);
         assert!(cycles.len() == 15);
+
+        // Visitor sees the same cycles, and can stop the search early
+        let mut visited = 0;
+        find_cycles_with_visitor(&graph, None, &mut |_cycle| {
+            visited += 1;
+            true
+        });
+        assert_eq!(visited, 15);
+        let mut visited = 0;
+        find_cycles_with_visitor(&graph, None, &mut |_cycle| {
+            visited += 1;
+            visited < 3
+        });
+        assert_eq!(visited, 3);
     }
     
+    #[test]
+    fn find_cycles_max_hops_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        graph.add_edge(nodes[4], nodes[5], 0.35);
+        graph.add_edge(nodes[5], nodes[4], 0.35);
+        graph.add_edge(nodes[4], nodes[7], 0.37);
+        graph.add_edge(nodes[5], nodes[7], 0.28);
+        graph.add_edge(nodes[7], nodes[5], 0.28);
+        graph.add_edge(nodes[5], nodes[1], 0.32);
+        graph.add_edge(nodes[0], nodes[4], 0.38);
+        graph.add_edge(nodes[0], nodes[2], 0.26);
+        graph.add_edge(nodes[7], nodes[3], 0.39);
+        graph.add_edge(nodes[1], nodes[3], 0.29);
+        graph.add_edge(nodes[2], nodes[7], 0.34);
+        graph.add_edge(nodes[6], nodes[2], -1.20);
+        graph.add_edge(nodes[3], nodes[6], 0.52);
+        graph.add_edge(nodes[6], nodes[0], -1.40);
+        graph.add_edge(nodes[6], nodes[4], -1.25);
+
+        // Bounded search finds exactly the short cycles of the unbounded search
+        let cycles = find_cycles(&graph);
+        for max_hops in 0..=8 {
+            let expected = cycles
+                .iter()
+                .filter(|cycle| cycle.edges().len() <= max_hops)
+                .count();
+            let mut visited = 0;
+            find_cycles_with_visitor(&graph, Some(max_hops), &mut |cycle| {
+                assert!(cycle.edges().len() <= max_hops);
+                visited += 1;
+                true
+            });
+            assert_eq!(visited, expected);
+        }
+    }
+
     #[test]
     fn get_negative_cycle_for_source_quick_test_0() {
         let mut graph: Graph<u32, f64> = Graph::new();
@@ -247,6 +328,326 @@ mod tests {
         assert!(cycle.unwrap().nodes().len() == 3);
     }
     
+    #[test]
+    fn get_negative_cycles_from_super_source_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        graph.add_edge(nodes[4], nodes[5], 0.35);
+        let edge_5_4 = graph.add_edge(nodes[5], nodes[4], 0.35);
+        graph.add_edge(nodes[4], nodes[7], 0.37);
+        graph.add_edge(nodes[5], nodes[7], 0.28);
+        graph.add_edge(nodes[7], nodes[5], 0.28);
+        graph.add_edge(nodes[5], nodes[1], 0.32);
+        graph.add_edge(nodes[0], nodes[4], 0.38);
+        graph.add_edge(nodes[0], nodes[2], 0.26);
+        graph.add_edge(nodes[7], nodes[3], 0.39);
+        graph.add_edge(nodes[1], nodes[3], 0.29);
+        graph.add_edge(nodes[2], nodes[7], 0.34);
+        graph.add_edge(nodes[6], nodes[2], 0.40);
+        graph.add_edge(nodes[3], nodes[6], 0.52);
+        graph.add_edge(nodes[6], nodes[0], 0.58);
+        graph.add_edge(nodes[6], nodes[4], 0.93);
+        assert!(get_negative_cycles_from_super_source(&graph).is_empty());
+
+        *graph.edge_weight_mut(edge_5_4).unwrap() = -0.66;
+        let cycles = get_negative_cycles_from_super_source(&graph);
+        assert!(!cycles.is_empty());
+        assert!(cycles.iter().all(|cycle| cycle.weight() < 0.0));
+        assert!(cycles.iter().all(|cycle| cycle.edges().contains(&edge_5_4)));
+
+        // Disconnected negative cycles, no single source reaches both
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..4 {
+            nodes.push(graph.add_node(i));
+        }
+        graph.add_edge(nodes[0], nodes[1], 0.1);
+        graph.add_edge(nodes[1], nodes[0], -0.3);
+        graph.add_edge(nodes[2], nodes[3], 0.1);
+        graph.add_edge(nodes[3], nodes[2], -0.5);
+
+        let cycles = get_negative_cycles_from_super_source(&graph);
+        assert_eq!(cycles.len(), 2);
+        assert!(cycles[0].weight() < cycles[1].weight());
+    }
+
+    #[test]
+    fn get_min_mean_cycle_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+        assert!(get_min_mean_cycle(&graph).is_none());
+
+        // No cycle
+        graph.add_edge(nodes[0], nodes[1], -0.5);
+        graph.add_edge(nodes[1], nodes[2], -0.5);
+        assert!(get_min_mean_cycle(&graph).is_none());
+
+        // Positive cycle is still the minimum mean cycle
+        graph.add_edge(nodes[2], nodes[0], 1.5);
+        let cycle = get_min_mean_cycle(&graph).unwrap();
+        assert_eq!(cycle.edges().len(), 3);
+        assert!(cycle.weight() > 0.0);
+
+        // 4-cycle of weight -1.0 (mean -0.25) beats 2-cycle of weight -0.6 (mean -0.3) on total, but not on mean
+        graph.add_edge(nodes[3], nodes[4], -0.25);
+        graph.add_edge(nodes[4], nodes[5], -0.25);
+        graph.add_edge(nodes[5], nodes[6], -0.25);
+        graph.add_edge(nodes[6], nodes[3], -0.25);
+        let edge_6_7 = graph.add_edge(nodes[6], nodes[7], -0.3);
+        let edge_7_6 = graph.add_edge(nodes[7], nodes[6], -0.3);
+        graph.add_edge(nodes[2], nodes[3], 0.1);
+        assert!(get_negative_cycle_quick(&graph).0);
+        assert_eq!(get_all_negative_cycles_0(&graph)[0].edges().len(), 4);
+
+        let cycle = get_min_mean_cycle(&graph).unwrap();
+        assert!(cycle.is_cycle());
+        assert_eq!(cycle.edges().len(), 2);
+        assert!(cycle.edges().contains(&edge_6_7));
+        assert!(cycle.edges().contains(&edge_7_6));
+        assert!((cycle.weight() + 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn budget_test_0() {
+        let budget = Budget::unlimited();
+        assert!((0..10_000).all(|_| budget.spend()));
+        budget.cancel();
+        assert!(!budget.spend());
+        assert!(budget.is_exhausted());
+
+        let budget = Budget::new(None, Some(3));
+        assert!(budget.spend() && budget.spend() && budget.spend());
+        assert!(!budget.is_exhausted());
+        assert!(!budget.spend());
+        assert!(budget.is_exhausted());
+
+        // Deadline is only checked every so often, but does run out
+        let budget = Budget::new(Some(Duration::ZERO), None);
+        assert!((0..10_000).any(|_| !budget.spend()));
+        assert!(budget.is_exhausted());
+    }
+
+    #[test]
+    fn get_negative_cycles_with_budget_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        graph.add_edge(nodes[4], nodes[5], 0.35);
+        graph.add_edge(nodes[5], nodes[4], -0.66);
+        graph.add_edge(nodes[4], nodes[7], 0.37);
+        graph.add_edge(nodes[5], nodes[7], 0.28);
+        graph.add_edge(nodes[7], nodes[5], 0.28);
+        graph.add_edge(nodes[5], nodes[1], 0.32);
+        graph.add_edge(nodes[0], nodes[4], 0.38);
+        graph.add_edge(nodes[0], nodes[2], 0.26);
+        graph.add_edge(nodes[7], nodes[3], 0.39);
+        graph.add_edge(nodes[1], nodes[3], 0.29);
+        graph.add_edge(nodes[2], nodes[7], 0.34);
+        graph.add_edge(nodes[6], nodes[2], 0.40);
+        graph.add_edge(nodes[3], nodes[6], 0.52);
+        graph.add_edge(nodes[6], nodes[0], 0.58);
+        graph.add_edge(nodes[6], nodes[4], 0.93);
+
+        let budget = Budget::unlimited();
+        assert_eq!(get_all_negative_cycles_with_budget(&graph, None, 0.0, &budget), get_all_negative_cycles_0(&graph));
+        assert_eq!(get_negative_cycles_from_super_source_with_budget(&graph, 0.0, &|_| true, &budget), get_negative_cycles_from_super_source(&graph));
+        assert!(!budget.is_exhausted());
+
+        let budget = Budget::new(None, Some(0));
+        assert!(get_all_negative_cycles_with_budget(&graph, None, 0.0, &budget).is_empty());
+        assert!(budget.is_exhausted());
+
+        // Every truncated enumeration is a prefix of the full one, as far as which cycles it found
+        let all_cycles = get_all_negative_cycles_0(&graph);
+        let mut found_counts: Vec<usize> = Vec::new();
+        for max_relaxations in 0..200 {
+            let budget = Budget::new(None, Some(max_relaxations));
+            let cycles = get_all_negative_cycles_with_budget(&graph, None, 0.0, &budget);
+            assert!(cycles.iter().all(|cycle| all_cycles.contains(cycle)));
+            if !budget.is_exhausted() {
+                assert_eq!(cycles, all_cycles);
+            }
+            found_counts.push(cycles.len());
+        }
+        assert!(found_counts.windows(2).all(|counts| counts[0] <= counts[1]));
+
+        // Super-source settles for the parent pointer cycles so far, all of which are negative
+        for max_relaxations in 0..50 {
+            let budget = Budget::new(None, Some(max_relaxations));
+            let cycles = get_negative_cycles_from_super_source_with_budget(&graph, 0.0, &|_| true, &budget);
+            assert!(cycles.iter().all(|cycle| cycle.weight() < 0.0));
+        }
+    }
+
+    #[test]
+    fn get_negative_cycles_with_epsilon_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        // 0 -> 1 -> 2 -> 0 trades back to where it started, but rounding leaves it a hair negative
+        let weight_0_1 = (1.1_f64).ln();
+        let weight_1_2 = (1.3_f64).ln();
+        graph.add_edge(nodes[0], nodes[1], weight_0_1);
+        graph.add_edge(nodes[1], nodes[2], weight_1_2);
+        graph.add_edge(nodes[2], nodes[0], -(weight_0_1 + weight_1_2) - 1e-15);
+        graph.add_edge(nodes[2], nodes[3], 0.1);
+        graph.add_edge(nodes[3], nodes[4], 0.1);
+
+        let budget = Budget::unlimited();
+        let epsilon = 1e-9;
+        assert_eq!(get_all_negative_cycles_with_budget(&graph, None, 0.0, &budget).len(), 1);
+        assert!(get_all_negative_cycles_with_budget(&graph, None, epsilon, &budget).is_empty());
+        assert!(get_top_k_negative_cycles_with_budget(&graph, 1, None, epsilon, &|_| true, &budget).is_empty());
+        assert!(get_negative_cycles_from_super_source_with_budget(&graph, epsilon, &|_| true, &budget).is_empty());
+        assert!(get_all_negative_cycles_1(&graph, epsilon).is_empty());
+        assert!(get_all_negative_cycles_through_node_with_budget(&graph, nodes[0], None, epsilon, &budget).is_empty());
+        assert!(get_negative_cycle_through_node_quick_with_budget(&graph, nodes[0], None, epsilon, &|_| true, &budget).is_none());
+        let all_edges: Vec<_> = graph.edge_indices().collect();
+        let mut potentials: Vec<f64> = Vec::new();
+        assert!(get_negative_cycle_incremental(&graph, &mut potentials, &all_edges, epsilon).is_none());
+
+        // A real arbitrage is still found
+        let edge_4_5 = graph.add_edge(nodes[4], nodes[5], 0.35);
+        let edge_5_4 = graph.add_edge(nodes[5], nodes[4], -0.66);
+        let cycles = get_all_negative_cycles_with_budget(&graph, None, epsilon, &budget);
+        assert_eq!(cycles.len(), 1);
+        assert!(cycles[0].edges().contains(&edge_4_5) && cycles[0].edges().contains(&edge_5_4));
+        assert_eq!(get_top_k_negative_cycles_with_budget(&graph, 2, None, epsilon, &|_| true, &budget), cycles);
+        assert_eq!(get_negative_cycles_from_super_source_with_budget(&graph, epsilon, &|_| true, &budget), cycles);
+        assert_eq!(get_all_negative_cycles_1(&graph, epsilon), cycles);
+        assert!(get_negative_cycle_incremental(&graph, &mut potentials, &[edge_4_5, edge_5_4], epsilon).is_some());
+        assert!(!budget.is_exhausted());
+
+        // Unless epsilon asks for more profit than it makes
+        assert!(get_all_negative_cycles_with_budget(&graph, None, 0.32, &budget).is_empty());
+        assert!(get_negative_cycles_from_super_source_with_budget(&graph, 0.32, &|_| true, &budget).is_empty());
+    }
+
+    #[test]
+    fn get_negative_cycles_with_epsilon_test_1() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        // 0 -> 1 -> 2 -> 0 makes 0.07% per hop, above epsilon in total but not per hop
+        let epsilon = (1.001_f64).ln();
+        let weight = -(1.0007_f64).ln();
+        graph.add_edge(nodes[0], nodes[1], weight);
+        graph.add_edge(nodes[1], nodes[2], weight);
+        graph.add_edge(nodes[2], nodes[0], weight);
+        // 3 -> 4 -> 3 is negative but below epsilon, so is passed over without relaxing forever
+        graph.add_edge(nodes[3], nodes[4], -(1.0001_f64).ln());
+        graph.add_edge(nodes[4], nodes[3], -(1.0001_f64).ln());
+        graph.add_edge(nodes[4], nodes[5], 0.1);
+
+        let budget = Budget::unlimited();
+        let cycles = get_all_negative_cycles_with_budget(&graph, None, epsilon, &budget);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(get_negative_cycles_from_super_source_with_budget(&graph, epsilon, &|_| true, &budget), cycles);
+        assert_eq!(get_all_negative_cycles_1(&graph, epsilon), cycles);
+        let cycle = get_negative_cycle_through_node_quick_with_budget(&graph, nodes[0], None, epsilon, &|_| true, &budget).unwrap();
+        assert_eq!(cycle.edges().len(), 3);
+        let all_edges: Vec<_> = graph.edge_indices().collect();
+        let mut potentials: Vec<f64> = Vec::new();
+        assert_eq!(get_negative_cycle_incremental(&graph, &mut potentials, &all_edges, epsilon).unwrap().edges().len(), 3);
+
+        // Only the cycle below epsilon is left
+        graph.update_edge(nodes[2], nodes[0], 0.1);
+        assert!(get_negative_cycles_from_super_source_with_budget(&graph, epsilon, &|_| true, &budget).is_empty());
+        assert!(get_all_negative_cycles_1(&graph, epsilon).is_empty());
+        assert!(get_negative_cycle_through_node_quick_with_budget(&graph, nodes[3], None, epsilon, &|_| true, &budget).is_none());
+        potentials.clear();
+        assert!(get_negative_cycle_incremental(&graph, &mut potentials, &all_edges, epsilon).is_none());
+        // Labels are left infeasible around it, so the next call has to start from scratch
+        assert!(potentials.is_empty());
+        assert!(!budget.is_exhausted());
+    }
+
+    #[test]
+    fn get_negative_cycles_with_accept_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        // 0 -> 1 -> 0 is the most negative, but turned down
+        let edge_0_1 = graph.add_edge(nodes[0], nodes[1], -0.5);
+        graph.add_edge(nodes[1], nodes[0], -0.5);
+        graph.add_edge(nodes[1], nodes[2], 0.1);
+        let edge_3_4 = graph.add_edge(nodes[3], nodes[4], 0.2);
+        graph.add_edge(nodes[4], nodes[3], -0.3);
+        graph.add_edge(nodes[2], nodes[3], 0.1);
+        let accept = |cycle: &Path<u32>| !cycle.edges().contains(&edge_0_1);
+
+        let budget = Budget::unlimited();
+        let top = get_top_k_negative_cycles_with_budget(&graph, 1, None, 0.0, &accept, &budget);
+        assert_eq!(top.len(), 1);
+        assert!(top[0].edges().contains(&edge_3_4));
+        assert_eq!(get_negative_cycles_from_super_source_with_budget(&graph, 0.0, &accept, &budget), top);
+        let cycle = get_negative_cycle_through_node_quick_with_budget(&graph, nodes[3], None, 0.0, &accept, &budget).unwrap();
+        assert!(cycle.edges().contains(&edge_3_4));
+        let all_edges: Vec<_> = graph.edge_indices().collect();
+        let mut potentials: Vec<f64> = Vec::new();
+        let cycle = get_negative_cycle_incremental_with_budget(&graph, &mut potentials, &all_edges, 0.0, &accept, &budget).unwrap();
+        assert!(cycle.edges().contains(&edge_3_4));
+        assert!(!budget.is_exhausted());
+    }
+
+    #[test]
+    fn strongly_connected_components_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        // Two cycles joined by a one-way edge, and dead ends on either side
+        graph.add_edge(nodes[0], nodes[1], 0.1);
+        graph.add_edge(nodes[1], nodes[2], 0.1);
+        graph.add_edge(nodes[2], nodes[0], -0.3);
+        graph.add_edge(nodes[2], nodes[3], 0.1);
+        graph.add_edge(nodes[3], nodes[4], 0.1);
+        graph.add_edge(nodes[4], nodes[3], -0.5);
+        graph.add_edge(nodes[5], nodes[0], 0.1);
+        graph.add_edge(nodes[4], nodes[6], 0.1);
+        let mut components = strongly_connected_components(&graph);
+        components.sort();
+        assert_eq!(components, vec![vec![nodes[0], nodes[1], nodes[2]], vec![nodes[3], nodes[4]]]);
+
+        // Self loop is a cycle of its own
+        graph.add_edge(nodes[7], nodes[7], -0.1);
+        assert_eq!(strongly_connected_components(&graph).len(), 3);
+
+        let mut cycles = search_by_component(&graph, |component, _| get_all_negative_cycles_0(component));
+        cycles.sort_unstable();
+        let expected = get_all_negative_cycles_0(&graph);
+        assert_eq!(cycles.len(), 3);
+        assert_eq!(cycles, expected);
+        for (cycle, expected_cycle) in cycles.iter().zip(expected.iter()) {
+            assert_eq!(cycle.nodes(), expected_cycle.nodes());
+            assert_eq!(cycle.weight(), expected_cycle.weight());
+        }
+
+        let empty: Graph<u32, f64> = Graph::new();
+        assert!(strongly_connected_components(&empty).is_empty());
+    }
+
     #[test]
     fn get_all_negative_cycles_test_0() {
         let mut graph: Graph<u32, f64> = Graph::new();
@@ -264,7 +665,7 @@ mod tests {
     
         let cycles = get_all_negative_cycles_0(&graph);
         assert!(cycles.is_empty());
-        let cycles = get_all_negative_cycles_1(&graph);
+        let cycles = get_all_negative_cycles_1(&graph, 0.0);
         assert!(cycles.is_empty());
     }
     
@@ -293,7 +694,7 @@ mod tests {
     
         let cycles = get_all_negative_cycles_0(&graph);
         assert!(cycles.is_empty());
-        let cycles = get_all_negative_cycles_1(&graph);
+        let cycles = get_all_negative_cycles_1(&graph, 0.0);
         assert!(cycles.is_empty());
     }
     
@@ -323,7 +724,297 @@ mod tests {
     
         let cycles = get_all_negative_cycles_0(&graph);
         assert!(cycles.len() == 2);
-        let cycles = get_all_negative_cycles_1(&graph);
+        let cycles = get_all_negative_cycles_1(&graph, 0.0);
         assert!(cycles.len() == 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn get_all_negative_cycles_test_3() {
+        // Two different cycles with the same weight
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..4 {
+            nodes.push(graph.add_node(i));
+        }
+
+        graph.add_edge(nodes[0], nodes[1], 0.1);
+        graph.add_edge(nodes[1], nodes[0], -0.3);
+        graph.add_edge(nodes[2], nodes[3], 0.1);
+        graph.add_edge(nodes[3], nodes[2], -0.3);
+        graph.add_edge(nodes[1], nodes[2], 0.5);
+
+        let cycles = get_all_negative_cycles_0(&graph);
+        assert_eq!(cycles.len(), 2);
+        // Each cycle is found from both of its nodes, but only reported once
+        let cycles = get_all_negative_cycles_1(&graph, 0.0);
+        assert_eq!(cycles.len(), 2);
+        assert!(cycles[0] != cycles[1]);
+    }
+
+    #[test]
+    fn parallel_edges_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..3 {
+            nodes.push(graph.add_node(i));
+        }
+
+        // Two pools for 0 -> 1, only the first one added is an arbitrage
+        let good_pool = graph.add_edge(nodes[0], nodes[1], 0.1);
+        graph.add_edge(nodes[0], nodes[1], 0.5);
+        graph.add_edge(nodes[1], nodes[0], -0.3);
+        graph.add_edge(nodes[1], nodes[2], 0.2);
+        graph.add_edge(nodes[2], nodes[0], 0.2);
+
+        // Each cycle is enumerated once, through the better pool
+        let cycles = find_cycles(&graph);
+        assert_eq!(cycles.len(), 2);
+        assert!(cycles.iter().all(|cycle| cycle.edges().contains(&good_pool)));
+
+        let cycles = get_all_negative_cycles_0(&graph);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].edges().len(), 2);
+        assert!(cycles[0].edges().contains(&good_pool));
+
+        let cycles = get_all_negative_cycles_1(&graph, 0.0);
+        assert_eq!(cycles.len(), 1);
+        assert!(cycles[0].edges().contains(&good_pool));
+
+        let (negative_cycle_found, cycle) = get_negative_cycle_quick(&graph);
+        assert!(negative_cycle_found);
+        assert!(cycle.unwrap().edges().contains(&good_pool));
+    }
+
+    #[test]
+    fn get_all_negative_cycles_bounded_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        graph.add_edge(nodes[4], nodes[5], 0.35);
+        graph.add_edge(nodes[5], nodes[4], -0.66);
+        graph.add_edge(nodes[4], nodes[7], 0.37);
+        graph.add_edge(nodes[5], nodes[7], 0.28);
+        graph.add_edge(nodes[7], nodes[5], 0.28);
+        graph.add_edge(nodes[5], nodes[1], 0.32);
+        graph.add_edge(nodes[0], nodes[4], 0.38);
+        graph.add_edge(nodes[0], nodes[2], 0.26);
+        graph.add_edge(nodes[7], nodes[3], 0.39);
+        graph.add_edge(nodes[1], nodes[3], 0.29);
+        graph.add_edge(nodes[2], nodes[7], 0.34);
+        graph.add_edge(nodes[6], nodes[2], 0.40);
+        graph.add_edge(nodes[3], nodes[6], 0.52);
+        graph.add_edge(nodes[6], nodes[0], 0.58);
+        graph.add_edge(nodes[6], nodes[4], 0.93);
+
+        // 4 -> 5 -> 4 and 4 -> 7 -> 5 -> 4
+        assert_eq!(get_all_negative_cycles_bounded(&graph, None).len(), 2);
+        assert_eq!(get_all_negative_cycles_bounded(&graph, Some(3)).len(), 2);
+        let cycles = get_all_negative_cycles_bounded(&graph, Some(2));
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].nodes().len(), 3);
+        assert!(get_all_negative_cycles_bounded(&graph, Some(1)).is_empty());
+    }
+
+    #[test]
+    fn get_negative_cycles_through_node_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        graph.add_edge(nodes[4], nodes[5], 0.35);
+        graph.add_edge(nodes[5], nodes[4], -0.66);
+        graph.add_edge(nodes[4], nodes[7], 0.37);
+        graph.add_edge(nodes[5], nodes[7], 0.28);
+        graph.add_edge(nodes[7], nodes[5], 0.28);
+        graph.add_edge(nodes[5], nodes[1], 0.32);
+        graph.add_edge(nodes[0], nodes[4], 0.38);
+        graph.add_edge(nodes[0], nodes[2], 0.26);
+        graph.add_edge(nodes[7], nodes[3], 0.39);
+        graph.add_edge(nodes[1], nodes[3], 0.29);
+        graph.add_edge(nodes[2], nodes[7], 0.34);
+        graph.add_edge(nodes[6], nodes[2], 0.40);
+        graph.add_edge(nodes[3], nodes[6], 0.52);
+        graph.add_edge(nodes[6], nodes[0], 0.58);
+        graph.add_edge(nodes[6], nodes[4], 0.93);
+
+        // 4 -> 5 -> 4 and 4 -> 7 -> 5 -> 4, rotated to start at 5
+        let cycles = get_all_negative_cycles_through_node(&graph, nodes[5], None);
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0].nodes(), vec![nodes[5], nodes[4], nodes[5]]);
+        assert_eq!(cycles[1].nodes(), vec![nodes[5], nodes[4], nodes[7], nodes[5]]);
+        assert_eq!(get_all_negative_cycles_through_node(&graph, nodes[5], Some(2)).len(), 1);
+
+        // Negative cycles are reachable from 0, but none pass through it
+        assert!(get_all_negative_cycles_through_node(&graph, nodes[0], None).is_empty());
+        assert!(get_negative_cycle_through_node_quick(&graph, nodes[0], None).is_none());
+
+        // Only 4 -> 7 -> 5 -> 4 passes through 7, whichever cycle Bellman-Ford from 7 runs into first
+        let cycle = get_negative_cycle_through_node_quick(&graph, nodes[7], None).unwrap();
+        assert_eq!(cycle.nodes(), vec![nodes[7], nodes[5], nodes[4], nodes[7]]);
+        assert!(get_negative_cycle_through_node_quick(&graph, nodes[7], Some(2)).is_none());
+    }
+
+    #[test]
+    fn get_top_k_negative_cycles_test_0() {
+        // Complete graph, with weights spread either side of 0.0 so there are plenty of negative cycles
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..7 {
+            nodes.push(graph.add_node(i));
+        }
+        for (i, source) in nodes.iter().enumerate() {
+            for (j, target) in nodes.iter().enumerate() {
+                if i != j {
+                    let weight = ((i * 7 + j * 13) % 17) as f64 / 10.0 - 0.8;
+                    graph.add_edge(*source, *target, weight);
+                }
+            }
+        }
+
+        // Pruned search returns the same weights as collecting everything and sorting
+        for max_hops in [None, Some(3)] {
+            let all_cycles = get_all_negative_cycles_bounded(&graph, max_hops);
+            assert!(all_cycles.len() > 20);
+            for k in [0, 1, 5, 20, all_cycles.len() + 1] {
+                let top_k = get_top_k_negative_cycles(&graph, k, max_hops);
+                assert_eq!(top_k.len(), k.min(all_cycles.len()));
+                for (cycle, expected) in top_k.iter().zip(all_cycles.iter()) {
+                    assert_eq!(cycle.weight(), expected.weight());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn get_negative_cycle_incremental_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+
+        graph.add_edge(nodes[4], nodes[5], 0.35);
+        let edge_5_4 = graph.add_edge(nodes[5], nodes[4], 0.35);
+        graph.add_edge(nodes[4], nodes[7], 0.37);
+        graph.add_edge(nodes[5], nodes[7], 0.28);
+        graph.add_edge(nodes[7], nodes[5], 0.28);
+        graph.add_edge(nodes[5], nodes[1], 0.32);
+        graph.add_edge(nodes[0], nodes[4], 0.38);
+        graph.add_edge(nodes[0], nodes[2], 0.26);
+        graph.add_edge(nodes[7], nodes[3], 0.39);
+        graph.add_edge(nodes[1], nodes[3], 0.29);
+        graph.add_edge(nodes[2], nodes[7], 0.34);
+        graph.add_edge(nodes[6], nodes[2], 0.40);
+        graph.add_edge(nodes[3], nodes[6], 0.52);
+        let edge_6_0 = graph.add_edge(nodes[6], nodes[0], 0.58);
+        graph.add_edge(nodes[6], nodes[4], 0.93);
+
+        // Full scan, no negative cycle
+        let mut potentials: Vec<f64> = Vec::new();
+        let all_edges: Vec<_> = graph.edge_indices().collect();
+        let cycle = get_negative_cycle_incremental(&graph, &mut potentials, &all_edges, 0.0);
+        assert!(cycle.is_none());
+        assert_eq!(potentials.len(), 8);
+
+        // Untouched edges don't need to be searched again
+        let cycle = get_negative_cycle_incremental(&graph, &mut potentials, &[], 0.0);
+        assert!(cycle.is_none());
+
+        // Price increase keeps labels feasible
+        *graph.edge_weight_mut(edge_6_0).unwrap() = 0.70;
+        let cycle = get_negative_cycle_incremental(&graph, &mut potentials, &[edge_6_0], 0.0);
+        assert!(cycle.is_none());
+
+        // Price drop creates negative cycles 4 -> 5 -> 4 and 4 -> 7 -> 5 -> 4
+        *graph.edge_weight_mut(edge_5_4).unwrap() = -0.66;
+        let cycle = get_negative_cycle_incremental(&graph, &mut potentials, &[edge_5_4], 0.0).unwrap();
+        assert!(cycle.weight() < 0.0);
+        assert!(cycle.edges().contains(&edge_5_4));
+        assert_eq!(cycle.nodes().first(), cycle.nodes().last());
+    }
+
+    // Node weight without Clone, cycles must be found on the parent pointers rather than a copied SPT graph
+    struct Token;
+
+    #[test]
+    fn get_all_negative_cycles_for_source_test_0() {
+        let mut graph: Graph<Token, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for _ in 0..4 {
+            nodes.push(graph.add_node(Token));
+        }
+
+        graph.add_edge(nodes[0], nodes[1], 0.5);
+        graph.add_edge(nodes[0], nodes[1], 0.1);
+        let edge_1_0 = graph.add_edge(nodes[1], nodes[0], -0.3);
+        graph.add_edge(nodes[1], nodes[2], 0.1);
+        graph.add_edge(nodes[2], nodes[3], 0.2);
+        let edge_3_2 = graph.add_edge(nodes[3], nodes[2], -0.5);
+
+        let cycles = get_all_negative_cycles_for_source(&graph, nodes[0], 0.0);
+        assert!(!cycles.is_empty());
+        for cycle in cycles.iter() {
+            assert!(cycle.weight() < 0.0);
+            assert_eq!(cycle.nodes().first(), cycle.nodes().last());
+            // Edges index into the original graph
+            let mut weight = 0.0;
+            for (i, edge) in cycle.edges().iter().enumerate() {
+                let (source, target) = graph.edge_endpoints(*edge).unwrap();
+                assert_eq!(source, cycle.nodes()[i]);
+                assert_eq!(target, cycle.nodes()[i + 1]);
+                weight += graph.edge_weight(*edge).unwrap();
+            }
+            assert_eq!(weight, cycle.weight());
+            assert!(cycle.edges().contains(&edge_1_0) || cycle.edges().contains(&edge_3_2));
+        }
+
+        let (negative_cycle_found, cycle) = get_negative_cycle_for_source_quick(&graph, nodes[0]);
+        assert!(negative_cycle_found);
+        let cycle = cycle.unwrap();
+        assert!(cycle.edges().contains(&edge_1_0) || cycle.edges().contains(&edge_3_2));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_scans_match_sequential_test_0() {
+        let mut graph: Graph<u32, f64> = Graph::new();
+        let mut nodes: Vec<NodeIndex> = Vec::new();
+        for i in 0..8 {
+            nodes.push(graph.add_node(i));
+        }
+        assert!(!get_negative_cycle_quick_parallel(&graph).0);
+        assert!(get_all_negative_cycles_1_parallel(&graph, 0.0).is_empty());
+
+        // Two components, each with a negative cycle, and a dead end
+        graph.add_edge(nodes[0], nodes[1], 0.1);
+        graph.add_edge(nodes[1], nodes[2], 0.1);
+        graph.add_edge(nodes[2], nodes[0], -0.3);
+        graph.add_edge(nodes[1], nodes[0], -0.2);
+        graph.add_edge(nodes[2], nodes[3], 0.1);
+        graph.add_edge(nodes[3], nodes[4], 0.1);
+        graph.add_edge(nodes[4], nodes[5], 0.2);
+        graph.add_edge(nodes[5], nodes[3], -0.5);
+        graph.add_edge(nodes[5], nodes[6], 0.1);
+        graph.add_edge(nodes[6], nodes[7], 0.1);
+
+        let (negative_cycle_found, cycle) = get_negative_cycle_quick_parallel(&graph);
+        assert!(negative_cycle_found);
+        assert!(cycle.unwrap().weight() < 0.0);
+
+        let cycles = get_all_negative_cycles_1_parallel(&graph, 0.0);
+        assert!(!cycles.is_empty());
+        assert_eq!(cycles, get_all_negative_cycles_1(&graph, 0.0));
+
+        let cycles = search_by_component_parallel(&graph, |component, _| get_all_negative_cycles_0(component));
+        assert_eq!(cycles.len(), 3);
+        assert_eq!(cycles, search_by_component(&graph, |component, _| get_all_negative_cycles_0(component)));
+        for (cycle, expected_cycle) in cycles.iter().zip(search_by_component(&graph, |component, _| get_all_negative_cycles_0(component)).iter()) {
+            assert_eq!(cycle.nodes(), expected_cycle.nodes());
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use crate::path::DecoratedPath;
+use serde::{Deserialize, Serialize};
+
+/// Gas used by one swap through a pool of protocol_type on node_type
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwapGas {
+    pub protocol_type: usize,
+    pub node_type: usize,
+    pub gas: u64,
+}
+
+/// What gas costs on one blockchain (node_type)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChainGas {
+    pub node_type: usize,
+    /// nodeId of the token gas is paid in, e.g. WETH on Ethereum
+    pub native_token: String,
+    /// Smallest unit of native_token per unit of gas, e.g. wei per gas
+    pub gas_price: f64,
+    /// Gas used by a transaction before any swap, e.g. 21000 on Ethereum
+    #[serde(default)]
+    pub base_gas: u64,
+}
+
+/// Estimates what an arbitrage costs in gas, see ScanOptions.net_profit_asset
+/// Default costs nothing, so net profit is just profit
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GasModel {
+    /// Gas per swap by protocol_type and node_type
+    pub swaps: Vec<SwapGas>,
+    /// Gas per swap through a protocol_type and node_type missing from swaps
+    pub default_swap_gas: u64,
+    /// Gas price by node_type, trades on a node_type missing from chains are free
+    pub chains: Vec<ChainGas>,
+}
+
+impl GasModel {
+    pub fn get_swap_gas(&self, protocol_type: usize, node_type: usize) -> u64 {
+        self.swaps
+            .iter()
+            .find(|swap| swap.protocol_type == protocol_type && swap.node_type == node_type)
+            .map_or(self.default_swap_gas, |swap| swap.gas)
+    }
+
+    pub fn get_chain(&self, node_type: usize) -> Option<&ChainGas> {
+        self.chains
+            .iter()
+            .find(|chain| chain.node_type == node_type)
+    }
+
+    /// Update the gas price of node_type, e.g. every block. Returns false if node_type is missing from chains
+    pub fn set_gas_price(&mut self, node_type: usize, gas_price: f64) -> bool {
+        match self
+            .chains
+            .iter_mut()
+            .find(|chain| chain.node_type == node_type)
+        {
+            Some(chain) => {
+                chain.gas_price = gas_price;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gas cost of trading every hop of path, as (native_token, amount) for each blockchain it trades on
+    /// Hops on the same blockchain are assumed to be one transaction, so pay base_gas once
+    pub fn get_gas_costs(&self, path: &DecoratedPath) -> Vec<(String, f64)> {
+        let mut node_types: Vec<usize> = path.edges.iter().map(|edge| edge.node_type).collect();
+        node_types.sort_unstable();
+        node_types.dedup();
+
+        node_types
+            .into_iter()
+            .filter_map(|node_type| {
+                let chain = self.get_chain(node_type)?;
+                let swap_gas: u64 = path
+                    .edges
+                    .iter()
+                    .filter(|edge| edge.node_type == node_type)
+                    .map(|edge| self.get_swap_gas(edge.protocol_type, node_type))
+                    .sum();
+                let gas = chain.base_gas + swap_gas;
+                Some((chain.native_token.clone(), gas as f64 * chain.gas_price))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+use crate::decorated_edge::DecoratedEdge;
+
+#[test]
+fn gas_model_get_gas_costs() {
+    let mut gas_model = GasModel {
+        swaps: vec![SwapGas {
+            protocol_type: 1,
+            node_type: 1,
+            gas: 100_000,
+        }],
+        default_swap_gas: 150_000,
+        chains: vec![ChainGas {
+            node_type: 1,
+            native_token: "WETH".to_string(),
+            gas_price: 2.0,
+            base_gas: 21_000,
+        }],
+    };
+    let new_edge = |protocol_type: usize, node_type: usize| {
+        DecoratedEdge::from_rate(
+            1.1,
+            protocol_type,
+            node_type,
+            "".to_string(),
+            "".to_string(),
+        )
+        .unwrap()
+    };
+    let path = DecoratedPath::new(
+        vec![
+            "a".to_string(),
+            "b".to_string(),
+            "c".to_string(),
+            "a".to_string(),
+        ],
+        vec![new_edge(1, 1), new_edge(2, 1), new_edge(1, 2)],
+    );
+
+    // node_type 2 has no gas price, so only the first two hops cost anything
+    assert_eq!(
+        gas_model.get_gas_costs(&path),
+        vec![(
+            "WETH".to_string(),
+            (21_000 + 100_000 + 150_000) as f64 * 2.0
+        )]
+    );
+
+    assert!(gas_model.set_gas_price(1, 0.0));
+    assert!(!gas_model.set_gas_price(2, 1.0));
+    assert_eq!(
+        gas_model.get_gas_costs(&path),
+        vec![("WETH".to_string(), 0.0)]
+    );
+    assert_eq!(GasModel::default().get_gas_costs(&path), Vec::new());
+}
@@ -1,7 +1,12 @@
 pub mod arbitrage_service;
 mod arbitrage_service_test;
-mod decorated_edge;
-mod engine;
+pub mod budget;
+pub mod decorated_edge;
+pub mod engine;
 mod engine_test;
-mod path;
+pub mod gas;
+pub mod path;
+pub mod scan_options;
+mod scratch;
+pub mod simulation;
 mod utils;
@@ -2,29 +2,116 @@ use petgraph::{
     graph::Graph,
     prelude::{EdgeIndex, NodeIndex},
 };
-use std::{cmp::Ordering, marker::PhantomData};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+};
 
-use crate::decorated_edge::DecoratedEdge;
+use crate::{
+    decorated_edge::DecoratedEdge,
+    simulation::{self, OptimalTrade, Simulation, SimulationError},
+};
 
-pub struct DecoratedPath<'a> {
-    pub edges: Vec<&'a DecoratedEdge>,
-    pub nodes: Vec<&'a String>,
+/// Arbitrage path resolved to nodeIds and edge metadata, returned to clients
+/// Owns its data so it can outlive the ArbitrageService borrow, be sent over the wire, logged or persisted
+/// nodes[i] -> nodes[i + 1] is traded through edges[i]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DecoratedPath {
+    pub edges: Vec<DecoratedEdge>,
+    pub nodes: Vec<String>,
+    /// Sum of edge weights, negative for an arbitrage
+    pub weight: f64,
+    /// exp(-weight), i.e. amount out per amount in after trading the whole path. > 1.0 for an arbitrage
+    pub profit_ratio: f64,
+    /// alternatives[i] are the other pools for nodes[i] -> nodes[i + 1], lowest weight first
+    /// edges[i] is always the lowest weight pool, so these are fallbacks, e.g. if edges[i] lacks liquidity
+    /// Empty unless requested with ScanOptions.include_alternatives
+    #[serde(default)]
+    pub alternatives: Vec<Vec<DecoratedEdge>>,
+    /// Amount in of nodes[0] that maximises profit, and the profit it makes, see simulation::optimal_trade
+    /// None unless every edge has reserves and some amount makes a profit
+    #[serde(default)]
+    pub optimal_trade: Option<OptimalTrade>,
+    /// Profit of optimal_trade less gas, in the smallest unit of ScanOptions.net_profit_asset
+    /// None unless requested with ScanOptions.net_profit_asset
+    #[serde(default)]
+    pub net_profit: Option<f64>,
+}
+
+impl DecoratedPath {
+    pub fn new(nodes: Vec<String>, edges: Vec<DecoratedEdge>) -> Self {
+        let weight: f64 = edges.iter().map(|edge| edge.weight).sum();
+        let mut path = DecoratedPath {
+            edges,
+            nodes,
+            weight,
+            profit_ratio: DecoratedEdge::weight_to_rate(weight),
+            alternatives: Vec::new(),
+            optimal_trade: None,
+            net_profit: None,
+        };
+        path.optimal_trade = path.get_optimal_trade().ok().flatten();
+        path
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Compact binary encoding (bincode), for persistence or high-volume logging
+    pub fn to_bytes(&self) -> bincode::Result<Vec<u8>> {
+        bincode::serialize(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> bincode::Result<Self> {
+        bincode::deserialize(bytes)
+    }
+
+    /// Trade `amount_in` of nodes[0] through every hop, with the integer maths the pools use
+    /// Every edge needs reserves, see DecoratedEdge.reserves
+    pub fn simulate(&self, amount_in: u128) -> Result<Simulation, SimulationError> {
+        simulation::simulate(self, amount_in)
+    }
+
+    /// Amount in that maximises profit, None if no amount makes a profit
+    /// Every edge needs reserves, see DecoratedEdge.reserves
+    pub fn get_optimal_trade(&self) -> Result<Option<OptimalTrade>, SimulationError> {
+        simulation::optimal_trade(self)
+    }
+
+    /// False if the path was found by its f64 weights, but exact integer maths on its pools' reserves shows
+    /// no amount in makes a profit. Paths through an edge without reserves can't be checked, so are true
+    pub fn is_verified(&self) -> bool {
+        // With reserves on every edge, optimal_trade is only None if the exact check found no profit
+        self.edges.iter().any(|edge| edge.reserves.is_none()) || self.optimal_trade.is_some()
+    }
 }
 
 /// Represents a collection of connected graph nodes, in otherwords the arbitrage path
+/// Two cycles are equal if they trade through the same edges in the same order, whichever node they start from
+/// Ordered by weight, most negative first, then by edges
 #[derive(Debug)]
-pub struct Path<N: Clone> {
+pub struct Path<N> {
     weight: f64,
     edges: Vec<EdgeIndex>,
+    /// edge_weights[i] is the weight of edges[i] when it was added
+    edge_weights: Vec<f64>,
     nodes: Vec<NodeIndex>,
     node_type: PhantomData<N>,
 }
 
-impl<N: Clone> Path<N> {
+impl<N> Path<N> {
     pub fn new(source_node: NodeIndex) -> Self {
         Path {
             weight: 0.0_f64,
             edges: Vec::new(),
+            edge_weights: Vec::new(),
             nodes: vec![source_node],
             node_type: PhantomData,
         }
@@ -36,9 +123,25 @@ impl<N: Clone> Path<N> {
             self.nodes.last().unwrap(),
             "Edge does not extend from existing path"
         );
-        self.weight += graph.edge_weight(edge).unwrap();
+        let edge_weight = *graph.edge_weight(edge).unwrap();
+        self.weight += edge_weight;
         self.edges.push(edge);
+        self.edge_weights.push(edge_weight);
         self.nodes.push(graph.edge_endpoints(edge).unwrap().1);
+
+        // Float addition isn't associative, so sum a cycle from its canonical start.
+        // Otherwise the same cycle found from two different nodes could differ in the last bit of weight
+        if self.is_cycle() {
+            let offset = self._canonical_offset();
+            self.weight = (0..self.edge_weights.len())
+                .map(|i| self.edge_weights[(offset + i) % self.edge_weights.len()])
+                .sum();
+        }
+    }
+
+    /// Path ends where it starts, after at least one edge
+    pub fn is_cycle(&self) -> bool {
+        !self.edges.is_empty() && self.nodes.first() == self.nodes.last()
     }
 
     pub fn weight(&self) -> f64 {
@@ -64,31 +167,105 @@ impl<N: Clone> Path<N> {
     pub fn length(&self) -> usize {
         self.nodes.len()
     }
+
+    /// For a cycle, start and end it at `node` instead, keeping the same trading order
+    /// Returns false and leaves the path untouched if `node` is not on the cycle
+    pub fn rotate_to(&mut self, node: NodeIndex) -> bool {
+        assert_eq!(
+            self.nodes.first(),
+            self.nodes.last(),
+            "Only a cycle can be rotated"
+        );
+        let position = match self.nodes[..self.nodes.len() - 1]
+            .iter()
+            .position(|cycle_node| *cycle_node == node)
+        {
+            None => return false,
+            Some(position) => position,
+        };
+
+        self._rotate(position);
+        true
+    }
+
+    /// For a cycle, start from the smallest EdgeIndex, so every rotation of the same cycle ends up identical
+    /// Paths that aren't cycles are left untouched
+    pub fn canonicalize(&mut self) {
+        if self.is_cycle() {
+            self._rotate(self._canonical_offset());
+        }
+    }
+
+    /// Start the cycle from edges[position], weight is unchanged since it is always summed from the canonical start
+    fn _rotate(&mut self, position: usize) {
+        // nodes[i] -> nodes[i + 1] is edges[i], so drop the repeated end node, rotate all, and close the cycle again
+        self.nodes.pop();
+        self.nodes.rotate_left(position);
+        self.nodes.push(self.nodes[0]);
+        self.edges.rotate_left(position);
+        self.edge_weights.rotate_left(position);
+    }
+
+    /// Position of the smallest EdgeIndex for a cycle, 0 otherwise
+    fn _canonical_offset(&self) -> usize {
+        if !self.is_cycle() {
+            return 0;
+        }
+        (0..self.edges.len())
+            .min_by_key(|i| self.edges[*i])
+            .unwrap()
+    }
+
+    /// Edges in canonical order, see canonicalize()
+    fn _canonical_edges(&self) -> impl Iterator<Item = &EdgeIndex> {
+        let offset = self._canonical_offset();
+        self.edges[offset..]
+            .iter()
+            .chain(self.edges[..offset].iter())
+    }
 }
 
-impl<N: Clone> PartialEq for Path<N> {
+impl<N> PartialEq for Path<N> {
     fn eq(&self, other: &Self) -> bool {
-        self.weight.to_bits() == other.weight.to_bits()
+        // A path without edges is identified by its only node
+        if self.edges.is_empty() || other.edges.is_empty() {
+            return self.nodes == other.nodes;
+        }
+        self.edges.len() == other.edges.len()
+            && self._canonical_edges().eq(other._canonical_edges())
     }
 }
 
-impl<N: Clone> Eq for Path<N> {}
+impl<N> Eq for Path<N> {}
+
+impl<N> Hash for Path<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        if self.edges.is_empty() {
+            self.nodes.hash(state);
+            return;
+        }
+        for edge in self._canonical_edges() {
+            edge.hash(state);
+        }
+    }
+}
 
-impl<N: Clone> PartialOrd for Path<N> {
+impl<N> PartialOrd for Path<N> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-impl<N: Clone> Ord for Path<N> {
+impl<N> Ord for Path<N> {
+    // Equal cycles have the same edge weights summed in the same order, so compare equal on weight too
     fn cmp(&self, other: &Self) -> Ordering {
-        if self.weight < other.weight {
-            Ordering::Less
-        } else if self.weight > other.weight {
-            Ordering::Greater
-        } else {
-            Ordering::Equal
-        }
+        self.weight
+            .total_cmp(&other.weight)
+            .then_with(|| self._canonical_edges().cmp(other._canonical_edges()))
+            .then_with(|| match self.edges.is_empty() {
+                true => self.nodes.cmp(&other.nodes),
+                false => Ordering::Equal,
+            })
     }
 }
 
@@ -138,3 +315,108 @@ fn path_basic_methods() {
     assert_eq!(path1.nodes()[1], destination_2);
     assert_eq!(path1.edges()[0], cost_2);
 }
+
+#[test]
+fn path_rotate_to() {
+    let mut graph: Graph<&str, f64> = Graph::new();
+    let a = graph.add_node("a");
+    let b = graph.add_node("b");
+    let c = graph.add_node("c");
+    let d = graph.add_node("d");
+    let a_b = graph.add_edge(a, b, 0.1);
+    let b_c = graph.add_edge(b, c, 0.2);
+    let c_a = graph.add_edge(c, a, -0.5);
+
+    let mut cycle: Path<&str> = Path::new(a);
+    cycle.add_to_path(&graph, a_b);
+    cycle.add_to_path(&graph, b_c);
+    cycle.add_to_path(&graph, c_a);
+
+    assert!(cycle.rotate_to(c));
+    assert_eq!(cycle.nodes(), vec![c, a, b, c]);
+    assert_eq!(cycle.edges(), vec![c_a, a_b, b_c]);
+    assert_eq!(cycle.weight(), 0.1 + 0.2 - 0.5);
+
+    assert!(!cycle.rotate_to(d));
+    assert_eq!(cycle.nodes(), vec![c, a, b, c]);
+}
+
+#[test]
+fn path_canonical_identity() {
+    let mut graph: Graph<&str, f64> = Graph::new();
+    let a = graph.add_node("a");
+    let b = graph.add_node("b");
+    let c = graph.add_node("c");
+    let d = graph.add_node("d");
+    let a_b = graph.add_edge(a, b, 0.1);
+    let b_c = graph.add_edge(b, c, 0.2);
+    let c_a = graph.add_edge(c, a, -0.7);
+    let c_d = graph.add_edge(c, d, 0.3);
+    let d_c = graph.add_edge(d, c, -0.7);
+    let d_b = graph.add_edge(d, b, -0.1);
+
+    let mut from_a: Path<&str> = Path::new(a);
+    for edge in [a_b, b_c, c_a] {
+        from_a.add_to_path(&graph, edge);
+    }
+    let mut from_c: Path<&str> = Path::new(c);
+    for edge in [c_a, a_b, b_c] {
+        from_c.add_to_path(&graph, edge);
+    }
+    // Same weight bits whichever node the cycle was found from
+    assert_eq!(from_a.weight().to_bits(), from_c.weight().to_bits());
+    assert!(from_a == from_c);
+    assert_eq!(from_a.cmp(&from_c), Ordering::Equal);
+    let unique: std::collections::HashSet<Path<&str>> = vec![from_a, from_c].into_iter().collect();
+    assert_eq!(unique.len(), 1);
+
+    // Different cycle with the same weight is a different path
+    let mut other: Path<&str> = Path::new(c);
+    for edge in [c_d, d_b, b_c] {
+        other.add_to_path(&graph, edge);
+    }
+    let mut from_c: Path<&str> = Path::new(c);
+    for edge in [c_a, a_b, b_c] {
+        from_c.add_to_path(&graph, edge);
+    }
+    assert!(other != from_c);
+    assert_ne!(other.cmp(&from_c), Ordering::Equal);
+
+    // Canonical form starts from the smallest EdgeIndex
+    from_c.canonicalize();
+    assert_eq!(from_c.edges(), vec![a_b, b_c, c_a]);
+    assert_eq!(from_c.nodes(), vec![a, b, c, a]);
+    let mut two_hop: Path<&str> = Path::new(d);
+    two_hop.add_to_path(&graph, d_c);
+    two_hop.add_to_path(&graph, c_d);
+    two_hop.canonicalize();
+    assert_eq!(two_hop.nodes(), vec![c, d, c]);
+}
+
+#[test]
+fn decorated_path_serialization_round_trip() {
+    let new_decorated_edge = |weight: f64, pool_address: &str| DecoratedEdge {
+        weight,
+        protocol_type: 1,
+        node_type: 1,
+        pool_address: pool_address.to_string(),
+        data: "".to_string(),
+        reserves: None,
+    };
+    let path = DecoratedPath::new(
+        vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        vec![
+            new_decorated_edge(0.5, "0x01"),
+            new_decorated_edge(-0.6, "0x02"),
+        ],
+    );
+    assert!((path.weight - -0.1).abs() < 1e-12);
+    assert!((path.profit_ratio - 0.1_f64.exp()).abs() < 1e-12);
+
+    let json = path.to_json().unwrap();
+    assert_eq!(DecoratedPath::from_json(&json).unwrap(), path);
+
+    let bytes = path.to_bytes().unwrap();
+    assert!(bytes.len() < json.len());
+    assert_eq!(DecoratedPath::from_bytes(&bytes).unwrap(), path);
+}
@@ -0,0 +1,50 @@
+use crate::{budget::Budget, path::DecoratedPath};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Knobs for an arbitrage scan, every field is optional so clients only send what they need
+/// Default scans the whole graph without limits, same as scan_arbitrages()
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScanOptions {
+    /// Only report arbitrages of at most max_hops trades, e.g. 2-5 since longer paths are never worth the gas
+    /// Prunes the search itself rather than filtering afterwards
+    pub max_hops: Option<usize>,
+    /// Only report arbitrages through at least one of these nodeIds, e.g. the base assets our capital sits in
+    /// Each arbitrage starts and ends at the first anchor it passes through. Empty for no constraint
+    pub anchors: Vec<String>,
+    /// Only report the top_k most profitable arbitrages
+    /// Prunes any part of the search that can't beat the top_k found so far, rather than enumerating every arbitrage
+    pub top_k: Option<usize>,
+    /// Also report the other pools for each hop, see DecoratedPath.alternatives
+    pub include_alternatives: bool,
+    /// Give up after this many milliseconds and report what was found so far, e.g. to finish before the next block
+    pub timeout_ms: Option<u64>,
+    /// Give up after examining this many edges and report what was found so far
+    /// Unlike timeout_ms, the same graph and options always give the same result, except with the parallel feature,
+    /// where components searched at the same time draw on one shared count and may run out at different points from run to run
+    pub max_relaxations: Option<u64>,
+    /// Value each arbitrage's profit after gas in this nodeId, e.g. USDC, see DecoratedPath.net_profit and GasModel
+    /// Then arbitrages are ranked by net profit rather than weight, and those that can't be valued are left out
+    pub net_profit_asset: Option<String>,
+    /// Only report arbitrages whose net profit is above this, in the smallest unit of net_profit_asset. Defaults to 0
+    pub min_net_profit: Option<f64>,
+}
+
+impl ScanOptions {
+    /// Budget for a scan starting now, from timeout_ms and max_relaxations
+    pub fn budget(&self) -> Budget<'static> {
+        Budget::new(
+            self.timeout_ms.map(Duration::from_millis),
+            self.max_relaxations,
+        )
+    }
+}
+
+/// Arbitrages found by a scan, and whether the scan gave up early because it ran out of time or relaxations
+/// A truncated scan may have missed arbitrages, and those it found may not be the most profitable
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub paths: Vec<DecoratedPath>,
+    pub truncated: bool,
+}
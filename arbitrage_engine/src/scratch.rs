@@ -0,0 +1,121 @@
+use petgraph::prelude::{EdgeIndex, NodeIndex};
+use std::{
+    cell::RefCell,
+    collections::{HashSet, VecDeque},
+    thread::LocalKey,
+};
+
+// Working memory for the engine functions, indexed by NodeIndex::index() since node indexes are dense.
+// Kept per thread and reused between calls, so repeated scans don't allocate once the buffers have grown to the graph size.
+
+thread_local! {
+    pub static SPFA_SCRATCH: RefCell<SpfaScratch> = RefCell::new(SpfaScratch::default());
+    pub static DFS_SCRATCH: RefCell<DfsScratch> = RefCell::new(DfsScratch::default());
+    pub static CIRCUIT_SCRATCH: RefCell<CircuitScratch> = RefCell::new(CircuitScratch::default());
+}
+
+// Borrow this thread's buffer for `f`.
+// A re-entrant call (e.g. an engine function called from a find_cycles visitor) gets a fresh buffer instead of panicking.
+pub fn with_scratch<S: Default + 'static, R>(
+    key: &'static LocalKey<RefCell<S>>,
+    f: impl FnOnce(&mut S) -> R,
+) -> R {
+    key.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut scratch) => f(&mut scratch),
+        Err(_) => f(&mut S::default()),
+    })
+}
+
+// Queue-based Bellman-Ford state
+#[derive(Default)]
+pub struct SpfaScratch {
+    // Node => Weight of current shortest path from source.
+    pub dist: Vec<f64>,
+    // Node => Edge in current shortest path with node as target_node.
+    pub edge_to: Vec<Option<EdgeIndex>>,
+    pub on_queue: Vec<bool>,
+    pub queue: VecDeque<NodeIndex>,
+    // Node => id of the parent-pointer walk that first reached it, 0 if not yet reached
+    pub walk_ids: Vec<usize>,
+    // Edges of negative cycles that were turned down, which must not relax again
+    pub banned: HashSet<EdgeIndex>,
+}
+
+impl SpfaScratch {
+    pub fn reset(&mut self, node_count: usize) {
+        self.dist.clear();
+        self.dist.resize(node_count, f64::MAX);
+        self.edge_to.clear();
+        self.edge_to.resize(node_count, None);
+        self.on_queue.clear();
+        self.on_queue.resize(node_count, false);
+        self.queue.clear();
+        self.banned.clear();
+    }
+}
+
+// has_cycle() DFS state
+#[derive(Default)]
+pub struct DfsScratch {
+    pub visited: Vec<bool>,
+    pub edge_to: Vec<Option<EdgeIndex>>,
+    pub on_stack: Vec<bool>,
+}
+
+impl DfsScratch {
+    pub fn reset(&mut self, node_count: usize) {
+        self.visited.clear();
+        self.visited.resize(node_count, false);
+        self.edge_to.clear();
+        self.edge_to.resize(node_count, None);
+        self.on_stack.clear();
+        self.on_stack.resize(node_count, false);
+    }
+}
+
+// find_cycles() circuit search state
+#[derive(Default)]
+pub struct CircuitScratch {
+    // Node => isBlocked
+    pub blocked: Vec<bool>,
+    // source_node => edges, represented by (destination_node, edge)
+    pub edges: Vec<Vec<(NodeIndex, EdgeIndex)>>,
+    // destination_node => position in edges[source_node], while building edges for one source_node
+    pub neighbor_positions: Vec<Option<usize>>,
+    // Node => nodes to unblock when node is unblocked
+    pub blocked_edges: Vec<Vec<NodeIndex>>,
+    // Nodes on the current circuit, bottom of the stack is the start node
+    pub stack: Vec<NodeIndex>,
+    // Edges between consecutive stack nodes
+    pub stack_edges: Vec<EdgeIndex>,
+    // Node => have we already called circuit with node as start
+    pub circuited: Vec<bool>,
+}
+
+impl CircuitScratch {
+    // Inner vectors are cleared rather than dropped, to keep their capacity
+    pub fn reset(&mut self, node_count: usize) {
+        self.blocked.clear();
+        self.blocked.resize(node_count, false);
+        self.circuited.clear();
+        self.circuited.resize(node_count, false);
+        self.stack.clear();
+        self.stack_edges.clear();
+        for neighbors in self.edges.iter_mut() {
+            neighbors.clear();
+        }
+        self.edges.resize_with(node_count, Vec::new);
+        self.neighbor_positions.clear();
+        self.neighbor_positions.resize(node_count, None);
+        self.unblock_all();
+        self.blocked_edges.resize_with(node_count, Vec::new);
+    }
+
+    // Reset all blocked markers for nodes and edges
+    pub fn unblock_all(&mut self) {
+        self.blocked.fill(false);
+        for blocked_edges in self.blocked_edges.iter_mut() {
+            blocked_edges.clear();
+        }
+    }
+}
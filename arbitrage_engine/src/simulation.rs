@@ -0,0 +1,358 @@
+use crate::{
+    decorated_edge::{u128_string, FEE_DENOMINATOR},
+    path::DecoratedPath,
+};
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+
+// 1 / golden ratio, the fraction of the interval kept by each step of a golden-section search
+const INVERSE_GOLDEN_RATIO: f64 = 0.618_033_988_749_895;
+
+/// Result of trading an amount through every hop of a DecoratedPath
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Simulation {
+    /// amounts[0] is the amount in, amounts[i + 1] the amount out of hop i, in the smallest unit of nodes[i + 1]
+    pub amounts: Vec<u128>,
+}
+
+impl Simulation {
+    pub fn amount_in(&self) -> u128 {
+        self.amounts[0]
+    }
+
+    pub fn amount_out(&self) -> u128 {
+        *self.amounts.last().unwrap()
+    }
+
+    /// amount_out - amount_in for a cycle, None if the trade loses money
+    pub fn profit(&self) -> Option<u128> {
+        self.amount_out().checked_sub(self.amount_in())
+    }
+}
+
+/// Why a path could not be simulated, and at which hop
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SimulationError {
+    /// edges[hop] has no PoolReserves
+    MissingReserves { hop: usize },
+    /// edges[hop] would revert, e.g. nothing left to trade after the previous hop rounded down to 0, or an empty pool
+    InvalidSwap { hop: usize },
+}
+
+/// Most profitable amount to trade around a cycle, with its outcome under the pools' integer maths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OptimalTrade {
+    #[serde(with = "u128_string")]
+    pub amount_in: u128,
+    #[serde(with = "u128_string")]
+    pub amount_out: u128,
+    /// amount_out - amount_in, in the smallest unit of nodes[0]
+    #[serde(with = "u128_string")]
+    pub profit: u128,
+}
+
+/// Trade `amount_in` of nodes[0] through each hop of `path` in turn, with the integer maths the pools use
+pub fn simulate(path: &DecoratedPath, amount_in: u128) -> Result<Simulation, SimulationError> {
+    let mut amounts: Vec<u128> = Vec::with_capacity(path.edges.len() + 1);
+    amounts.push(amount_in);
+    let mut amount = amount_in;
+    for (hop, edge) in path.edges.iter().enumerate() {
+        let reserves = edge
+            .reserves
+            .ok_or(SimulationError::MissingReserves { hop })?;
+        amount = reserves
+            .get_amount_out(amount)
+            .ok_or(SimulationError::InvalidSwap { hop })?;
+        amounts.push(amount);
+    }
+    Ok(Simulation { amounts })
+}
+
+/// Amount in that maximises profit around the cycle `path`, None if no amount makes a profit
+///
+/// Each constant-product hop maps x => a * x / (b + c * x), and so does any composition of them,
+/// so the whole cycle is one such map and profit a * x / (b + c * x) - x peaks at x = (sqrt(a * b) - b) / c.
+/// That is worked out in f64 and checked with the exact simulation, so profit can be a few units short
+/// of the best integer amount since each pool rounds down. Should rounding leave it unprofitable,
+/// falls back to a golden-section search on the exact simulation, since profit is unimodal in x.
+pub fn optimal_trade(path: &DecoratedPath) -> Result<Option<OptimalTrade>, SimulationError> {
+    // Coefficients of the composed map, normalised so b == 1 to keep them in f64 range
+    let (mut a, mut c) = (1.0_f64, 0.0_f64);
+    for (hop, edge) in path.edges.iter().enumerate() {
+        let reserves = edge
+            .reserves
+            .ok_or(SimulationError::MissingReserves { hop })?;
+        let fee_factor = f64::from(FEE_DENOMINATOR - reserves.fee_bps.min(FEE_DENOMINATOR))
+            / f64::from(FEE_DENOMINATOR);
+        // Hop is (fee_factor * reserve_out, reserve_in, fee_factor), and composing (a, 1, c) with it gives
+        // (a * fee_factor * reserve_out, reserve_in, c * reserve_in + fee_factor * a), normalised by reserve_in
+        let reserve_in = reserves.reserve_in as f64;
+        (a, c) = (
+            a * fee_factor * reserves.reserve_out as f64 / reserve_in,
+            c + fee_factor * a / reserve_in,
+        );
+    }
+
+    // a is the marginal rate of the whole cycle, so there is no profit to be had unless it is above 1.
+    // Checked exactly, as a may be off by rounding either way
+    if !_is_marginal_rate_above_one(path) {
+        return Ok(None);
+    }
+    if c > 0.0 {
+        let candidate = (a.sqrt() - 1.0) / c;
+        if let Some(trade) = _trade_at(path, candidate as u128) {
+            return Ok(Some(trade));
+        }
+    }
+
+    // Rounding, or coefficients out of f64 range. Trading more than the first pool holds is never worth it
+    let first_reserve = path.edges.first().and_then(|edge| edge.reserves);
+    let upper = first_reserve.map_or(0, |reserves| reserves.reserve_in);
+    Ok(_golden_section_search(path, 1, upper))
+}
+
+// Product of the marginal rates of every hop above 1, worked out exactly as a fraction of big integers
+// Reserves are already known to be there
+fn _is_marginal_rate_above_one(path: &DecoratedPath) -> bool {
+    let mut numerator = BigUint::from(1_u32);
+    let mut denominator = BigUint::from(1_u32);
+    for reserves in path.edges.iter().filter_map(|edge| edge.reserves) {
+        numerator *= BigUint::from(reserves.reserve_out)
+            * (FEE_DENOMINATOR - reserves.fee_bps.min(FEE_DENOMINATOR));
+        denominator *= BigUint::from(reserves.reserve_in) * FEE_DENOMINATOR;
+    }
+    numerator > denominator
+}
+
+// Outcome of trading amount_in, None unless it makes a profit
+fn _trade_at(path: &DecoratedPath, amount_in: u128) -> Option<OptimalTrade> {
+    let simulation = simulate(path, amount_in).ok()?;
+    let profit = simulation.profit().filter(|profit| *profit > 0)?;
+    Some(OptimalTrade {
+        amount_in,
+        amount_out: simulation.amount_out(),
+        profit,
+    })
+}
+
+// Most profitable amount in [lower, upper], assuming profit rises then falls
+fn _golden_section_search(
+    path: &DecoratedPath,
+    mut lower: u128,
+    mut upper: u128,
+) -> Option<OptimalTrade> {
+    let profit_at = |amount_in: u128| _trade_at(path, amount_in).map_or(0, |trade| trade.profit);
+    while upper > lower + 2 {
+        let step = ((upper - lower) as f64 * (1.0 - INVERSE_GOLDEN_RATIO)) as u128;
+        let (left, right) = (lower + step.max(1), upper - step.max(1));
+        if profit_at(left) < profit_at(right) {
+            lower = left;
+        } else {
+            upper = right;
+        }
+    }
+    (lower..=upper)
+        .filter_map(|amount_in| _trade_at(path, amount_in))
+        .max_by_key(|trade| trade.profit)
+}
+
+#[cfg(test)]
+use crate::decorated_edge::{DecoratedEdge, PoolReserves};
+
+#[cfg(test)]
+fn pool_edge(reserve_in: u128, reserve_out: u128, pool_address: &str) -> DecoratedEdge {
+    let reserves = PoolReserves {
+        reserve_in,
+        reserve_out,
+        fee_bps: 30,
+    };
+    DecoratedEdge::from_reserves(reserves, 1, 1, pool_address.to_string(), "".to_string()).unwrap()
+}
+
+#[test]
+fn pool_reserves_get_amount_out() {
+    let reserves = PoolReserves {
+        reserve_in: 1_000,
+        reserve_out: 2_000,
+        fee_bps: 30,
+    };
+    // 100 * 997 * 2000 / (1000 * 1000 + 100 * 997) = 181.3..., rounded down
+    assert_eq!(reserves.get_amount_out(100), Some(181));
+    assert_eq!(reserves.get_amount_out(0), None);
+    assert!((reserves.rate() - 1.994).abs() < 1e-12);
+
+    // Same as the 997 / 1000 form in UniswapV2Library, with products well past u128
+    let reserves = PoolReserves {
+        reserve_in: 5_000_000 * 10u128.pow(18),
+        reserve_out: 2_000 * 10u128.pow(18),
+        fee_bps: 30,
+    };
+    // 1e22 * 997 * 2e21 / (5e24 * 1000 + 1e22 * 997), worked out with arbitrary precision
+    let amount_out = reserves.get_amount_out(10_000 * 10u128.pow(18)).unwrap();
+    assert_eq!(amount_out, 3_980_063_752_876_763_733);
+    assert!(amount_out < reserves.reserve_out);
+
+    let empty = PoolReserves {
+        reserve_in: 0,
+        reserve_out: 2_000,
+        fee_bps: 30,
+    };
+    assert_eq!(empty.get_amount_out(100), None);
+    let all_fee = PoolReserves {
+        reserve_in: 1_000,
+        reserve_out: 2_000,
+        fee_bps: 10_000,
+    };
+    assert_eq!(all_fee.get_amount_out(100), None);
+}
+
+#[test]
+fn simulate_decorated_path() {
+    // a -> b at 2.0, b -> a at 0.6 before fees, so a marginal arbitrage of about 1.19
+    let path = DecoratedPath::new(
+        vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        vec![
+            pool_edge(1_000_000, 2_000_000, "0x01"),
+            pool_edge(5_000_000, 3_000_000, "0x02"),
+        ],
+    );
+    assert!(path.profit_ratio > 1.19 && path.profit_ratio < 1.2);
+
+    let simulation = path.simulate(1_000).unwrap();
+    assert_eq!(simulation.amounts.len(), 3);
+    assert_eq!(simulation.amount_in(), 1_000);
+    assert_eq!(
+        simulation.amounts[1],
+        path.edges[0]
+            .reserves
+            .unwrap()
+            .get_amount_out(1_000)
+            .unwrap()
+    );
+    assert_eq!(
+        simulation.amount_out(),
+        path.edges[1]
+            .reserves
+            .unwrap()
+            .get_amount_out(simulation.amounts[1])
+            .unwrap()
+    );
+    assert!(simulation.profit().unwrap() > 0);
+
+    // Slippage eats the profit of a large trade
+    let simulation = path.simulate(1_000_000).unwrap();
+    assert_eq!(simulation.profit(), None);
+
+    // Nothing to trade, which the pool would revert
+    assert_eq!(
+        path.simulate(0),
+        Err(SimulationError::InvalidSwap { hop: 0 })
+    );
+
+    let mut path = path;
+    path.edges[1].reserves = None;
+    assert_eq!(
+        path.simulate(1_000),
+        Err(SimulationError::MissingReserves { hop: 1 })
+    );
+}
+
+#[test]
+fn optimal_trade_matches_brute_force() {
+    let path = DecoratedPath::new(
+        vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        vec![
+            pool_edge(100_000, 200_000, "0x01"),
+            pool_edge(500_000, 300_000, "0x02"),
+        ],
+    );
+    let trade = path.optimal_trade.unwrap();
+    assert_eq!(path.get_optimal_trade(), Ok(Some(trade)));
+    assert_eq!(trade.profit, trade.amount_out - trade.amount_in);
+    assert_eq!(
+        path.simulate(trade.amount_in).unwrap().amount_out(),
+        trade.amount_out
+    );
+
+    // Each pool rounds down, so the best integer amount can beat the closed form by up to a unit per hop
+    let best_profit = (1..100_000)
+        .filter_map(|amount_in| path.simulate(amount_in).unwrap().profit())
+        .max()
+        .unwrap();
+    assert!(trade.profit <= best_profit);
+    assert!(trade.profit + path.edges.len() as u128 >= best_profit);
+
+    // Golden-section search gets as close without the closed form
+    let searched = _golden_section_search(&path, 1, 100_000).unwrap();
+    assert!(searched.profit <= best_profit);
+    assert!(searched.profit + path.edges.len() as u128 >= best_profit);
+
+    // 18 decimal tokens, with products of reserves far past u128
+    let e18 = 10u128.pow(18);
+    let path = DecoratedPath::new(
+        vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        vec![
+            pool_edge(5_000_000 * e18, 2_000 * e18, "0x01"),
+            pool_edge(1_800 * e18, 5_000_000 * e18, "0x02"),
+        ],
+    );
+    let trade = path.optimal_trade.unwrap();
+    for amount_in in [trade.amount_in / 2, trade.amount_in * 2] {
+        assert!(path.simulate(amount_in).unwrap().profit().unwrap() < trade.profit);
+    }
+}
+
+#[test]
+fn optimal_trade_without_profit() {
+    // a -> b at 2.0, b -> a at 0.5, so fees make every trade lose money
+    let path = DecoratedPath::new(
+        vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        vec![
+            pool_edge(1_000_000, 2_000_000, "0x01"),
+            pool_edge(2_000_000, 1_000_000, "0x02"),
+        ],
+    );
+    assert_eq!(path.optimal_trade, None);
+    assert_eq!(path.get_optimal_trade(), Ok(None));
+    assert!(!path.is_verified());
+
+    // Without reserves there is only the f64 weight to go on
+    let mut path = path;
+    path.edges[0].reserves = None;
+    assert_eq!(
+        path.get_optimal_trade(),
+        Err(SimulationError::MissingReserves { hop: 0 })
+    );
+    assert!(path.is_verified());
+}
+
+#[test]
+fn optimal_trade_exact_marginal_rate() {
+    let fee_free_edge = |reserve_in: u128, reserve_out: u128| {
+        let reserves = PoolReserves {
+            reserve_in,
+            reserve_out,
+            fee_bps: 0,
+        };
+        DecoratedEdge::from_reserves(reserves, 1, 1, "".to_string(), "".to_string()).unwrap()
+    };
+    let e30 = 10u128.pow(30);
+
+    // Marginal rate is 1 - 1e-30, which f64 rounds to 1
+    let path = DecoratedPath::new(
+        vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        vec![fee_free_edge(e30 + 1, e30), fee_free_edge(e30, e30)],
+    );
+    assert_eq!(path.weight, 0.0);
+    assert!(!_is_marginal_rate_above_one(&path));
+    assert!(!path.is_verified());
+
+    // 1 + 1e-30 is above 1, but too little for any whole amount to profit from
+    let path = DecoratedPath::new(
+        vec!["a".to_string(), "b".to_string(), "a".to_string()],
+        vec![fee_free_edge(e30, e30 + 1), fee_free_edge(e30, e30)],
+    );
+    assert!(_is_marginal_rate_above_one(&path));
+    assert_eq!(path.optimal_trade, None);
+    assert!(!path.is_verified());
+}
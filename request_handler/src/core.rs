@@ -1,33 +1,136 @@
-// TODO - How to serialize and deserialize message to find appropriate message handler?
+use crate::message::{decode_request, encode_response, Request, Response};
+use arbitrage_engine::{
+    arbitrage_service::{ArbitrageService, IArbitrageService},
+    decorated_edge::DecoratedEdge,
+};
 use dotenv::dotenv;
 use std::fs;
 use zeromq::*;
 
-// struct ArbitrageResult {
-    // time
-    // array of results
-// }
-
-pub struct RequestHandler {}
+/// Decodes IPC messages and routes them to the ArbitrageService it owns
+pub struct RequestHandler {
+    service: ArbitrageService,
+}
 
 impl RequestHandler {
-    pub async fn listen_and_serve() -> Result<(), Box<dyn std::error::Error>> {
+    pub fn new(service: ArbitrageService) -> Self {
+        RequestHandler { service }
+    }
+
+    pub fn service(&self) -> &ArbitrageService {
+        &self.service
+    }
+
+    pub fn handle(&mut self, request: Request) -> Response {
+        match request {
+            Request::UpsertPath { n0, n1, edge } => Response::PathUpserted {
+                updated: self.service.upsert_path(&n0, &n1, edge),
+            },
+            Request::UpsertRate {
+                n0,
+                n1,
+                rate,
+                protocol_type,
+                node_type,
+                pool_address,
+                data,
+            } => match DecoratedEdge::from_rate(rate, protocol_type, node_type, pool_address, data)
+            {
+                Some(edge) => Response::PathUpserted {
+                    updated: self.service.upsert_path(&n0, &n1, edge),
+                },
+                None => Response::Error {
+                    message: format!("Invalid exchange rate {}", rate),
+                },
+            },
+            Request::RemovePath {
+                n0,
+                n1,
+                pool_address,
+            } => Response::PathRemoved {
+                removed: self.service.remove_edge(&n0, &n1, &pool_address),
+            },
+            Request::RemoveNode { n } => Response::NodeRemoved {
+                removed: self.service.remove_node(&n),
+            },
+            Request::ScanArbitrages { options } => {
+                let result = self.service.scan_arbitrages_with_options(&options);
+                Response::Arbitrages {
+                    paths: result.paths,
+                    truncated: result.truncated,
+                }
+            }
+            Request::ScanArbitragesQuick { options } => {
+                let result = self.service.scan_arbitrages_quick_with_options(&options);
+                Response::Arbitrages {
+                    paths: result.paths,
+                    truncated: result.truncated,
+                }
+            }
+            Request::ScanArbitragesIncremental { options } => {
+                let result = self
+                    .service
+                    .scan_arbitrages_incremental_with_options(&options);
+                Response::Arbitrages {
+                    paths: result.paths,
+                    truncated: result.truncated,
+                }
+            }
+            Request::GetStats => Response::Stats {
+                node_count: self.service.node_count(),
+                edge_count: self.service.edge_count(),
+            },
+            Request::SetGasModel { gas_model } => {
+                self.service.set_gas_model(gas_model);
+                Response::GasModelSet
+            }
+            Request::SetGasPrice {
+                node_type,
+                gas_price,
+            } => Response::GasPriceSet {
+                updated: self.service.set_gas_price(node_type, gas_price),
+            },
+            Request::SetMinProfitRatio { ratio } => Response::MinProfitRatioSet {
+                updated: self.service.set_min_profit_ratio(ratio),
+            },
+        }
+    }
+
+    /// Raw IPC frame in, raw IPC frame out
+    /// Malformed requests get an Error response rather than taking down the server
+    pub fn handle_raw(&mut self, raw: &str) -> String {
+        let response = match decode_request(raw) {
+            Ok(request) => self.handle(request),
+            Err(message) => Response::Error { message },
+        };
+        encode_response(response)
+    }
+
+    /// Serve requests on IPC_ENDPOINT (read from environment / .env) until the socket errors
+    pub async fn listen_and_serve(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         dotenv().ok();
-        let ipc_endpoint = std::env::var("IPC_ENDPOINT").unwrap();
-        let ipc_filepath = ipc_endpoint[6..].to_string();
-        if fs::metadata(&ipc_filepath).is_ok() {
-            fs::remove_file(&ipc_filepath)?;
+        let ipc_endpoint = std::env::var("IPC_ENDPOINT")?;
+        self.serve(&ipc_endpoint).await
+    }
+
+    /// Bind a REP socket to an `ipc://` endpoint and answer one request at a time
+    /// Requests are handled sequentially, so every request sees the graph as left by the previous one
+    pub async fn serve(&mut self, ipc_endpoint: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let ipc_filepath = ipc_endpoint
+            .strip_prefix("ipc://")
+            .ok_or_else(|| format!("Expected ipc:// endpoint, got {}", ipc_endpoint))?;
+        if fs::metadata(ipc_filepath).is_ok() {
+            fs::remove_file(ipc_filepath)?;
             println!("Removing existing IPC file at {}", ipc_filepath);
         }
 
         let mut socket = zeromq::RepSocket::new();
-        socket.bind(&ipc_endpoint).await?;
+        socket.bind(ipc_endpoint).await?;
         println!("API server started at {}", ipc_filepath);
         loop {
-            let mut repl: String = socket.recv().await?.try_into()?;
-            dbg!(&repl);
-            repl.push_str(" Reply");
-            socket.send(repl.into()).await?;
+            let request: String = socket.recv().await?.try_into()?;
+            let reply = self.handle_raw(&request);
+            socket.send(reply.into()).await?;
         }
     }
 }
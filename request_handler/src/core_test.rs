@@ -0,0 +1,363 @@
+#[cfg(test)]
+mod tests {
+    use crate::{
+        core::RequestHandler,
+        message::{Response, ResponseMessage, PROTOCOL_VERSION},
+    };
+    use arbitrage_engine::arbitrage_service::ArbitrageService;
+    use std::time::Duration;
+    use zeromq::{ReqSocket, Socket, SocketRecv, SocketSend};
+
+    fn upsert_request(n0: &str, n1: &str, weight: f64) -> String {
+        format!(
+            r#"{{"version": {}, "request": {{"type": "UpsertPath", "n0": "{}", "n1": "{}", "edge": {{"weight": {}, "protocol_type": 1, "node_type": 1, "pool_address": ""}}}}}}"#,
+            PROTOCOL_VERSION, n0, n1, weight
+        )
+    }
+
+    fn decode(raw: &str) -> Response {
+        let message: ResponseMessage = serde_json::from_str(raw).unwrap();
+        assert_eq!(message.version, PROTOCOL_VERSION);
+        message.response
+    }
+
+    #[test]
+    fn test_handle_raw_upsert_and_stats() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        let response = decode(&handler.handle_raw(&upsert_request("a", "b", 0.5)));
+        assert!(matches!(response, Response::PathUpserted { updated: true }));
+
+        let stats = format!(
+            r#"{{"version": {}, "request": {{"type": "GetStats"}}}}"#,
+            PROTOCOL_VERSION
+        );
+        let response = decode(&handler.handle_raw(&stats));
+        assert!(matches!(
+            response,
+            Response::Stats {
+                node_count: 2,
+                edge_count: 1
+            }
+        ));
+    }
+
+    #[test]
+    fn test_handle_raw_upsert_with_reserves() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        let upsert = |n0: &str, n1: &str, weight: f64, reserve_in: u128, reserve_out: u128| {
+            format!(
+                r#"{{"version": {}, "request": {{"type": "UpsertPath", "n0": "{}", "n1": "{}", "edge": {{"weight": {}, "protocol_type": 1, "node_type": 1, "pool_address": "", "reserves": {{"reserve_in": "{}", "reserve_out": "{}", "fee_bps": 30}}}}}}}}"#,
+                PROTOCOL_VERSION, n0, n1, weight, reserve_in, reserve_out
+            )
+        };
+        // Reserves past u64, as for most 18 decimal tokens
+        let reserve = 10u128.pow(24);
+        handler.handle_raw(&upsert("a", "b", 0.5, reserve, reserve));
+        handler.handle_raw(&upsert("b", "a", -0.6, reserve, 2 * reserve));
+
+        let scan = format!(
+            r#"{{"version": {}, "request": {{"type": "ScanArbitrages"}}}}"#,
+            PROTOCOL_VERSION
+        );
+        match decode(&handler.handle_raw(&scan)) {
+            Response::Arbitrages { paths, .. } => {
+                assert_eq!(paths.len(), 1);
+                let reserves: Vec<u128> = paths[0]
+                    .edges
+                    .iter()
+                    .map(|edge| edge.reserves.unwrap().reserve_out)
+                    .collect();
+                assert!(reserves.contains(&(2 * reserve)));
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn test_handle_raw_scan_arbitrages() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        handler.handle_raw(&upsert_request("a", "b", 0.5));
+        handler.handle_raw(&upsert_request("b", "a", -0.6));
+
+        let scan = format!(
+            r#"{{"version": {}, "request": {{"type": "ScanArbitrages"}}}}"#,
+            PROTOCOL_VERSION
+        );
+        match decode(&handler.handle_raw(&scan)) {
+            Response::Arbitrages { paths, .. } => {
+                assert_eq!(paths.len(), 1);
+                assert_eq!(paths[0].nodes.len(), 3);
+                assert_eq!(paths[0].edges.len(), 2);
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+
+        // options may be left out of an incremental scan too
+        let scan = format!(
+            r#"{{"version": {}, "request": {{"type": "ScanArbitragesIncremental"}}}}"#,
+            PROTOCOL_VERSION
+        );
+        match decode(&handler.handle_raw(&scan)) {
+            Response::Arbitrages { paths, truncated } => {
+                assert_eq!(paths.len(), 1);
+                assert!(!truncated);
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn test_handle_raw_scan_arbitrages_with_options() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        handler.handle_raw(&upsert_request("a", "b", 0.5));
+        handler.handle_raw(&upsert_request("b", "a", -0.6));
+
+        let scan = |max_hops: usize| {
+            format!(
+                r#"{{"version": {}, "request": {{"type": "ScanArbitrages", "options": {{"max_hops": {}}}}}}}"#,
+                PROTOCOL_VERSION, max_hops
+            )
+        };
+        match decode(&handler.handle_raw(&scan(2))) {
+            Response::Arbitrages { paths, .. } => assert_eq!(paths.len(), 1),
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match decode(&handler.handle_raw(&scan(1))) {
+            Response::Arbitrages { paths, .. } => assert!(paths.is_empty()),
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn test_handle_raw_scan_arbitrages_with_budget() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        handler.handle_raw(&upsert_request("a", "b", 0.5));
+        handler.handle_raw(&upsert_request("b", "a", -0.6));
+
+        let scan = |request_type: &str, max_relaxations: u64| {
+            format!(
+                r#"{{"version": {}, "request": {{"type": "{}", "options": {{"max_relaxations": {}}}}}}}"#,
+                PROTOCOL_VERSION, request_type, max_relaxations
+            )
+        };
+        for request_type in ["ScanArbitrages", "ScanArbitragesQuick", "ScanArbitragesIncremental"] {
+            match decode(&handler.handle_raw(&scan(request_type, 1000))) {
+                Response::Arbitrages { paths, truncated } => {
+                    assert_eq!(paths.len(), 1);
+                    assert!(!truncated);
+                }
+                response => panic!("Unexpected response {:?}", response),
+            }
+            match decode(&handler.handle_raw(&scan(request_type, 0))) {
+                Response::Arbitrages { paths, truncated } => {
+                    assert!(paths.is_empty());
+                    assert!(truncated);
+                }
+                response => panic!("Unexpected response {:?}", response),
+            }
+        }
+    }
+
+    #[test]
+    fn test_handle_raw_set_gas_model() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        let set_gas_model = format!(
+            r#"{{"version": {}, "request": {{"type": "SetGasModel", "gas_model": {{"swaps": [{{"protocol_type": 1, "node_type": 1, "gas": 100000}}], "chains": [{{"node_type": 1, "native_token": "WETH", "gas_price": 30000000000, "base_gas": 21000}}]}}}}}}"#,
+            PROTOCOL_VERSION
+        );
+        let response = decode(&handler.handle_raw(&set_gas_model));
+        assert!(matches!(response, Response::GasModelSet));
+        assert_eq!(handler.service().gas_model().get_swap_gas(1, 1), 100_000);
+
+        let set_gas_price = |node_type: usize| {
+            format!(
+                r#"{{"version": {}, "request": {{"type": "SetGasPrice", "node_type": {}, "gas_price": 20000000000}}}}"#,
+                PROTOCOL_VERSION, node_type
+            )
+        };
+        let response = decode(&handler.handle_raw(&set_gas_price(1)));
+        assert!(matches!(response, Response::GasPriceSet { updated: true }));
+        assert_eq!(handler.service().gas_model().get_chain(1).unwrap().gas_price, 2e10);
+        let response = decode(&handler.handle_raw(&set_gas_price(2)));
+        assert!(matches!(response, Response::GasPriceSet { updated: false }));
+    }
+
+    #[test]
+    fn test_handle_raw_set_min_profit_ratio() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        handler.handle_raw(&upsert_request("a", "b", 0.35));
+        handler.handle_raw(&upsert_request("b", "a", -0.66));
+        let set_min_profit_ratio = |ratio: f64| {
+            format!(
+                r#"{{"version": {}, "request": {{"type": "SetMinProfitRatio", "ratio": {}}}}}"#,
+                PROTOCOL_VERSION, ratio
+            )
+        };
+        let scan = format!(
+            r#"{{"version": {}, "request": {{"type": "ScanArbitrages"}}}}"#,
+            PROTOCOL_VERSION
+        );
+
+        // a -> b -> a has a profit_ratio of exp(0.31), about 1.36
+        let response = decode(&handler.handle_raw(&set_min_profit_ratio(1.5)));
+        assert!(matches!(response, Response::MinProfitRatioSet { updated: true }));
+        match decode(&handler.handle_raw(&scan)) {
+            Response::Arbitrages { paths, .. } => assert!(paths.is_empty()),
+            response => panic!("Unexpected response {:?}", response),
+        }
+
+        let response = decode(&handler.handle_raw(&set_min_profit_ratio(1.2)));
+        assert!(matches!(response, Response::MinProfitRatioSet { updated: true }));
+        match decode(&handler.handle_raw(&scan)) {
+            Response::Arbitrages { paths, .. } => assert_eq!(paths.len(), 1),
+            response => panic!("Unexpected response {:?}", response),
+        }
+
+        let response = decode(&handler.handle_raw(&set_min_profit_ratio(0.5)));
+        assert!(matches!(response, Response::MinProfitRatioSet { updated: false }));
+    }
+
+    #[test]
+    fn test_handle_raw_scan_arbitrages_quick_with_anchors() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        handler.handle_raw(&upsert_request("a", "b", 0.5));
+        handler.handle_raw(&upsert_request("b", "a", -0.6));
+        handler.handle_raw(&upsert_request("b", "c", 0.1));
+
+        let scan = |anchor: &str| {
+            format!(
+                r#"{{"version": {}, "request": {{"type": "ScanArbitragesQuick", "options": {{"anchors": ["{}"]}}}}}}"#,
+                PROTOCOL_VERSION, anchor
+            )
+        };
+        match decode(&handler.handle_raw(&scan("b"))) {
+            Response::Arbitrages { paths, .. } => {
+                assert_eq!(paths.len(), 1);
+                assert_eq!(paths[0].nodes, vec!["b", "a", "b"]);
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+        match decode(&handler.handle_raw(&scan("c"))) {
+            Response::Arbitrages { paths, .. } => assert!(paths.is_empty()),
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn test_handle_raw_upsert_rate() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        let upsert_rate = |n0: &str, n1: &str, rate: f64| {
+            format!(
+                r#"{{"version": {}, "request": {{"type": "UpsertRate", "n0": "{}", "n1": "{}", "rate": {}, "protocol_type": 1, "node_type": 1, "pool_address": "0x01"}}}}"#,
+                PROTOCOL_VERSION, n0, n1, rate
+            )
+        };
+        let response = decode(&handler.handle_raw(&upsert_rate("a", "b", 2.0)));
+        assert!(matches!(response, Response::PathUpserted { updated: true }));
+        let response = decode(&handler.handle_raw(&upsert_rate("b", "a", 0.6)));
+        assert!(matches!(response, Response::PathUpserted { updated: true }));
+        let response = decode(&handler.handle_raw(&upsert_rate("b", "a", -1.0)));
+        assert!(matches!(response, Response::Error { .. }));
+
+        let scan = format!(
+            r#"{{"version": {}, "request": {{"type": "ScanArbitrages"}}}}"#,
+            PROTOCOL_VERSION
+        );
+        match decode(&handler.handle_raw(&scan)) {
+            Response::Arbitrages { paths, .. } => {
+                assert_eq!(paths.len(), 1);
+                assert!((paths[0].profit_ratio - 1.2).abs() < 1e-9);
+            }
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+
+    #[test]
+    fn test_handle_raw_remove_path_and_node() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        handler.handle_raw(&upsert_request("a", "b", 0.5));
+        handler.handle_raw(&upsert_request("b", "c", 0.4));
+
+        let remove_path = format!(
+            r#"{{"version": {}, "request": {{"type": "RemovePath", "n0": "a", "n1": "b", "pool_address": ""}}}}"#,
+            PROTOCOL_VERSION
+        );
+        let response = decode(&handler.handle_raw(&remove_path));
+        assert!(matches!(response, Response::PathRemoved { removed: true }));
+        let response = decode(&handler.handle_raw(&remove_path));
+        assert!(matches!(response, Response::PathRemoved { removed: false }));
+
+        let remove_node = format!(
+            r#"{{"version": {}, "request": {{"type": "RemoveNode", "n": "c"}}}}"#,
+            PROTOCOL_VERSION
+        );
+        let response = decode(&handler.handle_raw(&remove_node));
+        assert!(matches!(response, Response::NodeRemoved { removed: true }));
+        assert_eq!(handler.service().node_count(), 2);
+        assert_eq!(handler.service().edge_count(), 0);
+    }
+
+    #[test]
+    fn test_handle_raw_malformed_request_returns_error() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        let response = decode(&handler.handle_raw("not json"));
+        assert!(matches!(response, Response::Error { .. }));
+    }
+
+    #[test]
+    fn test_handle_raw_wrong_version_returns_error() {
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+        let request = format!(
+            r#"{{"version": {}, "request": {{"type": "GetStats"}}}}"#,
+            PROTOCOL_VERSION + 1
+        );
+        let response = decode(&handler.handle_raw(&request));
+        assert!(matches!(response, Response::Error { .. }));
+    }
+
+    // Drive the whole stack through a real IPC socket, as an external client would
+    #[tokio::test]
+    async fn test_serve_end_to_end_over_ipc() {
+        let ipc_filepath =
+            std::env::temp_dir().join(format!("arbitrage-e2e-{}.sock", std::process::id()));
+        let ipc_endpoint = format!("ipc://{}", ipc_filepath.display());
+        let mut handler = RequestHandler::new(ArbitrageService::new());
+
+        let client = async {
+            let mut socket = ReqSocket::new();
+            // Server may not have bound yet
+            while socket.connect(&ipc_endpoint).await.is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+
+            let mut responses: Vec<Response> = Vec::new();
+            let scan = format!(
+                r#"{{"version": {}, "request": {{"type": "ScanArbitragesQuick"}}}}"#,
+                PROTOCOL_VERSION
+            );
+            for request in [
+                upsert_request("a", "b", 0.5),
+                upsert_request("b", "a", -0.6),
+                scan,
+            ] {
+                socket.send(request.into()).await.unwrap();
+                let reply: String = socket.recv().await.unwrap().try_into().unwrap();
+                responses.push(decode(&reply));
+            }
+            responses
+        };
+
+        let responses = tokio::select! {
+            result = handler.serve(&ipc_endpoint) => panic!("Server stopped: {:?}", result.err()),
+            responses = client => responses,
+        };
+        let _ = std::fs::remove_file(&ipc_filepath);
+
+        assert!(matches!(responses[0], Response::PathUpserted { updated: true }));
+        assert!(matches!(responses[1], Response::PathUpserted { updated: true }));
+        match &responses[2] {
+            Response::Arbitrages { paths, .. } => assert_eq!(paths.len(), 1),
+            response => panic!("Unexpected response {:?}", response),
+        }
+    }
+}
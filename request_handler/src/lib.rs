@@ -1 +1,3 @@
 pub mod core;
+mod core_test;
+pub mod message;
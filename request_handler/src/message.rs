@@ -0,0 +1,121 @@
+use arbitrage_engine::{
+    decorated_edge::DecoratedEdge, gas::GasModel, path::DecoratedPath, scan_options::ScanOptions,
+};
+use serde::{Deserialize, Serialize};
+
+/// Version of the message schema below
+/// Bump whenever a message changes in a way that older clients cannot parse
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Envelope for every request received over IPC, encoded as JSON
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RequestMessage {
+    pub version: u32,
+    pub request: Request,
+}
+
+/// Envelope for every response sent over IPC, encoded as JSON
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseMessage {
+    pub version: u32,
+    pub response: Response,
+}
+
+/// Requests that can be routed to the ArbitrageService
+/// Tagged by `type`, e.g. {"type": "ScanArbitrages"}
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Request {
+    /// Add or update edge n0 -> n1
+    UpsertPath {
+        n0: String,
+        n1: String,
+        edge: DecoratedEdge,
+    },
+    /// Add or update edge n0 -> n1 from a raw exchange rate
+    /// rate is amount of n1 out per amount of n0 in, after fees. Converted to a -ln(rate) edge weight by the engine
+    UpsertRate {
+        n0: String,
+        n1: String,
+        rate: f64,
+        protocol_type: usize,
+        node_type: usize,
+        pool_address: String,
+        #[serde(default)]
+        data: String,
+    },
+    /// Remove the n0 -> n1 edge for a pool
+    RemovePath {
+        n0: String,
+        n1: String,
+        pool_address: String,
+    },
+    /// Remove a node and every edge into or out of it
+    RemoveNode { n: String },
+    /// Returns all arbitrages found, within the limits of options if given
+    ScanArbitrages {
+        #[serde(default)]
+        options: ScanOptions,
+    },
+    /// Stops at first arbitrage found, within the limits of options if given
+    ScanArbitragesQuick {
+        #[serde(default)]
+        options: ScanOptions,
+    },
+    /// Stops at first arbitrage found, only searching around edges changed since the previous incremental scan
+    /// Within the timeout and relaxation budget of options if given
+    ScanArbitragesIncremental {
+        #[serde(default)]
+        options: ScanOptions,
+    },
+    /// Size of the in-memory graph
+    GetStats,
+    /// Replace the gas cost estimates used to rank arbitrages by net profit, see ScanOptions.net_profit_asset
+    SetGasModel { gas_model: GasModel },
+    /// Update the gas price of one blockchain in the gas model, e.g. every block
+    SetGasPrice { node_type: usize, gas_price: f64 },
+    /// Only report arbitrages with a profit_ratio above ratio, e.g. 1.001, see ArbitrageService::set_min_profit_ratio
+    SetMinProfitRatio { ratio: f64 },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Response {
+    PathUpserted { updated: bool },
+    PathRemoved { removed: bool },
+    NodeRemoved { removed: bool },
+    /// truncated if the scan ran out of time or relaxations before finishing, see ScanOptions
+    Arbitrages {
+        paths: Vec<DecoratedPath>,
+        #[serde(default)]
+        truncated: bool,
+    },
+    Stats { node_count: usize, edge_count: usize },
+    GasModelSet,
+    /// updated is false if the gas model has no such node_type
+    GasPriceSet { updated: bool },
+    /// updated is false if ratio is below 1.0
+    MinProfitRatioSet { updated: bool },
+    Error { message: String },
+}
+
+/// Parse a raw IPC frame, rejecting messages from a different protocol version
+pub fn decode_request(raw: &str) -> Result<Request, String> {
+    let message: RequestMessage =
+        serde_json::from_str(raw).map_err(|err| format!("Malformed request: {}", err))?;
+    if message.version != PROTOCOL_VERSION {
+        return Err(format!(
+            "Unsupported protocol version {}, expected {}",
+            message.version, PROTOCOL_VERSION
+        ));
+    }
+    Ok(message.request)
+}
+
+pub fn encode_response(response: Response) -> String {
+    let message = ResponseMessage {
+        version: PROTOCOL_VERSION,
+        response,
+    };
+    serde_json::to_string(&message).expect("Response should always serialize")
+}